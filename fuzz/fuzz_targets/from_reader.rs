@@ -0,0 +1,18 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use std::io::Cursor;
+use valve_pak::VPK;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(vpk) = VPK::from_reader(&mut Cursor::new(data)) {
+        // Walking the parsed tree must not panic either
+        for path in vpk.file_paths() {
+            let _ = vpk.resolve_path(path);
+        }
+        for entry in &vpk {
+            let _ = entry.size();
+            let _ = entry.location();
+        }
+    }
+});
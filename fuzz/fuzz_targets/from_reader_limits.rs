@@ -0,0 +1,18 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use std::io::Cursor;
+use valve_pak::OpenOptions;
+use valve_pak::vpk::Limits;
+
+// Tight limits exercise the limit checks on small inputs
+fuzz_target!(|data: &[u8]| {
+    let limits = Limits {
+        max_tree_length: 4096,
+        max_entries: 16,
+        max_string_length: 64,
+    };
+    let _ = OpenOptions::new()
+        .limits(limits)
+        .from_reader(&mut Cursor::new(data));
+});
@@ -0,0 +1,352 @@
+//! Shared handles to the directory file and archive chunks of an opened VPK
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufReader, Read};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use crate::error::{Result, VpkError};
+use crate::file::VPKFile;
+use crate::utils::EMBEDDED_ARCHIVE_INDEX;
+
+/// Opens the file holding the data of archive `index`
+pub(crate) fn open_archive(vpk_path: &Path, index: u16) -> Result<File> {
+    let path = VPKFile::resolve_archive_path(vpk_path, index);
+    File::open(&path).map_err(|source| {
+        if index == EMBEDDED_ARCHIVE_INDEX {
+            VpkError::io(
+                format!("Failed to open VPK archive: {}", path.display()),
+                source,
+            )
+        } else {
+            VpkError::MissingArchive {
+                index,
+                path: path.clone(),
+                source,
+            }
+        }
+    })
+}
+
+/// Archive files opened by a VPK, one handle per chunk shared by every reader.
+///
+/// At most `max_open` handles are kept; opening another one closes the least
+/// recently used. Readers still holding an evicted handle keep it open until
+/// they are dropped. Reads go through [`read_at`], so readers on different
+/// threads never move each other's position.
+#[derive(Debug)]
+pub(crate) struct ArchiveHandles {
+    max_open: usize,
+    state: Mutex<HandleState>,
+}
+
+#[derive(Debug, Default)]
+struct HandleState {
+    /// Open handles with the tick of their last use
+    handles: HashMap<u16, (Arc<File>, u64)>,
+    tick: u64,
+}
+
+impl ArchiveHandles {
+    pub(crate) fn new(max_open: usize) -> Self {
+        ArchiveHandles {
+            max_open: max_open.max(1),
+            state: Mutex::new(HandleState::default()),
+        }
+    }
+
+    /// Gets the handle for archive `index`, opening it if it is not cached
+    pub(crate) fn get(&self, vpk_path: &Path, index: u16) -> Result<Arc<File>> {
+        let mut state = self.state.lock().expect("archive handles lock poisoned");
+        state.tick += 1;
+        let tick = state.tick;
+
+        if let Some((handle, last_used)) = state.handles.get_mut(&index) {
+            *last_used = tick;
+            return Ok(Arc::clone(handle));
+        }
+
+        let handle = Arc::new(open_archive(vpk_path, index)?);
+        if state.handles.len() >= self.max_open {
+            let least_recent = state
+                .handles
+                .iter()
+                .min_by_key(|(_, (_, last_used))| *last_used)
+                .map(|(index, _)| *index);
+            if let Some(least_recent) = least_recent {
+                state.handles.remove(&least_recent);
+            }
+        }
+        state.handles.insert(index, (Arc::clone(&handle), tick));
+        Ok(handle)
+    }
+
+    /// Number of handles currently cached
+    #[cfg(test)]
+    fn open_count(&self) -> usize {
+        self.state
+            .lock()
+            .expect("archive handles lock poisoned")
+            .handles
+            .len()
+    }
+}
+
+/// Reads from `file` at `offset` without using or moving its cursor
+pub(crate) fn read_at(file: &File, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+    #[cfg(unix)]
+    {
+        std::os::unix::fs::FileExt::read_at(file, buf, offset)
+    }
+    #[cfg(windows)]
+    {
+        // Moves the cursor, which no reader relies on
+        std::os::windows::fs::FileExt::seek_read(file, buf, offset)
+    }
+}
+
+/// A byte range of an archive file, read positionally
+pub(crate) struct ArchiveRange<'a> {
+    file: &'a File,
+    offset: u64,
+    remaining: u64,
+}
+
+impl<'a> ArchiveRange<'a> {
+    pub(crate) fn new(file: &'a File, offset: u64, length: u64) -> Self {
+        ArchiveRange {
+            file,
+            offset,
+            remaining: length,
+        }
+    }
+}
+
+impl Read for ArchiveRange<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = buf
+            .len()
+            .min(self.remaining.try_into().unwrap_or(usize::MAX));
+        if len == 0 {
+            return Ok(0);
+        }
+        let read = read_at(self.file, &mut buf[..len], self.offset)?;
+        self.offset += read as u64;
+        self.remaining -= read as u64;
+        Ok(read)
+    }
+}
+
+/// Buffer size used when entry data has to be copied through userspace
+const COPY_BUFFER_SIZE: usize = 64 * 1024;
+
+/// Copies `length` bytes at `offset` of `source` to the current position of
+/// `output` and returns the number of bytes copied, which is less than
+/// `length` only if `source` ends first.
+///
+/// On Linux the data goes straight from one file to the other with
+/// `copy_file_range`, or `sendfile` where that is not supported, such as
+/// across filesystems on older kernels. Elsewhere, or if both fail, it is
+/// copied through a buffer. `source`'s cursor is never used.
+pub(crate) fn copy_range(
+    source: &File,
+    offset: u64,
+    length: u64,
+    output: &mut File,
+) -> io::Result<u64> {
+    #[cfg(target_os = "linux")]
+    {
+        if let Some(copied) = linux::copy_file_range(source, offset, length, output)? {
+            return Ok(copied);
+        }
+        if let Some(copied) = linux::sendfile(source, offset, length, output)? {
+            return Ok(copied);
+        }
+    }
+    copy_buffered(source, offset, length, output)
+}
+
+/// Copies a range through a userspace buffer
+fn copy_buffered(source: &File, offset: u64, length: u64, output: &mut File) -> io::Result<u64> {
+    let mut range =
+        BufReader::with_capacity(COPY_BUFFER_SIZE, ArchiveRange::new(source, offset, length));
+    io::copy(&mut range, output)
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use std::fs::File;
+    use std::io;
+    use std::os::fd::AsRawFd;
+
+    /// Largest count passed to one system call, below the kernel's own cap
+    const MAX_CHUNK: u64 = 1 << 30;
+
+    /// Returns true for errors meaning the call cannot be used for these
+    /// files at all, as opposed to a failure while copying
+    fn is_unsupported(error: &io::Error) -> bool {
+        matches!(
+            error.raw_os_error(),
+            Some(libc::ENOSYS | libc::EXDEV | libc::EOPNOTSUPP | libc::EINVAL | libc::EPERM)
+        )
+    }
+
+    /// Copies with `copy_file_range`, or returns `None` if it is unsupported
+    pub(super) fn copy_file_range(
+        source: &File,
+        offset: u64,
+        length: u64,
+        output: &mut File,
+    ) -> io::Result<Option<u64>> {
+        copy_loop(length, |copied, count| {
+            let mut source_offset = (offset + copied) as libc::loff_t;
+            // SAFETY: both descriptors are open for the duration of the call,
+            // and a null output offset uses and advances `output`'s cursor
+            unsafe {
+                libc::copy_file_range(
+                    source.as_raw_fd(),
+                    &mut source_offset,
+                    output.as_raw_fd(),
+                    std::ptr::null_mut(),
+                    count,
+                    0,
+                )
+            }
+        })
+    }
+
+    /// Copies with `sendfile`, or returns `None` if it is unsupported
+    pub(super) fn sendfile(
+        source: &File,
+        offset: u64,
+        length: u64,
+        output: &mut File,
+    ) -> io::Result<Option<u64>> {
+        copy_loop(length, |copied, count| {
+            let mut source_offset = (offset + copied) as libc::off_t;
+            // SAFETY: both descriptors are open for the duration of the call,
+            // and `sendfile` writes at and advances `output`'s cursor
+            unsafe {
+                libc::sendfile(
+                    output.as_raw_fd(),
+                    source.as_raw_fd(),
+                    &mut source_offset,
+                    count,
+                )
+            }
+        })
+    }
+
+    /// Calls `copy(copied, count)` until `length` bytes are copied or the
+    /// source ends. Unsupported errors before anything was copied give `None`,
+    /// as does copying nothing on the first call, which some filesystems do
+    /// instead of failing.
+    pub(super) fn copy_loop(
+        length: u64,
+        mut copy: impl FnMut(u64, usize) -> isize,
+    ) -> io::Result<Option<u64>> {
+        let mut copied = 0;
+        while copied < length {
+            let count = (length - copied).min(MAX_CHUNK) as usize;
+            match copy(copied, count) {
+                -1 => {
+                    let error = io::Error::last_os_error();
+                    match error.kind() {
+                        io::ErrorKind::Interrupted => continue,
+                        _ if copied == 0 && is_unsupported(&error) => return Ok(None),
+                        _ => return Err(error),
+                    }
+                }
+                0 if copied == 0 => return Ok(None),
+                0 => break,
+                written => copied += written as u64,
+            }
+        }
+        Ok(Some(copied))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_handle_cache_evicts_least_recently_used() -> Result<()> {
+        let temp_dir = tempfile::TempDir::new()?;
+        let vpk_path = temp_dir.path().join("pak01_dir.vpk");
+        for index in 0..3 {
+            std::fs::write(
+                VPKFile::resolve_archive_path(&vpk_path, index),
+                [index as u8],
+            )?;
+        }
+
+        let handles = ArchiveHandles::new(2);
+        let first = handles.get(&vpk_path, 0)?;
+        handles.get(&vpk_path, 1)?;
+        // Touch chunk 0 so chunk 1 is the least recently used
+        assert!(Arc::ptr_eq(&first, &handles.get(&vpk_path, 0)?));
+        handles.get(&vpk_path, 2)?;
+        assert_eq!(handles.open_count(), 2);
+
+        assert!(Arc::ptr_eq(&first, &handles.get(&vpk_path, 0)?));
+        let mut byte = [0u8; 1];
+        read_at(&*handles.get(&vpk_path, 1)?, &mut byte, 0)?;
+        assert_eq!(byte, [1]);
+        assert_eq!(handles.open_count(), 2);
+
+        assert!(matches!(
+            handles.get(&vpk_path, 7),
+            Err(VpkError::MissingArchive { index: 7, .. })
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_copy_range() -> Result<()> {
+        let temp_dir = tempfile::TempDir::new()?;
+        let source_path = temp_dir.path().join("source");
+        let data: Vec<u8> = (0..200_000u32).map(|i| (i % 251) as u8).collect();
+        std::fs::write(&source_path, &data)?;
+        let source = File::open(&source_path)?;
+
+        for (name, copy) in [
+            (
+                "fast",
+                copy_range as fn(&File, u64, u64, &mut File) -> io::Result<u64>,
+            ),
+            ("buffered", copy_buffered),
+        ] {
+            let output_path = temp_dir.path().join(name);
+            let mut output = File::create(&output_path)?;
+            std::io::Write::write_all(&mut output, b"head")?;
+            assert_eq!(copy(&source, 1000, 150_000, &mut output)?, 150_000);
+            // Copying past the end of the source stops early
+            assert_eq!(copy(&source, 199_990, 100, &mut output)?, 10);
+            drop(output);
+
+            let written = std::fs::read(&output_path)?;
+            assert_eq!(&written[..4], b"head", "{name}");
+            assert_eq!(&written[4..150_004], &data[1000..151_000], "{name}");
+            assert_eq!(&written[150_004..], &data[199_990..], "{name}");
+        }
+
+        Ok(())
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_copy_loop_empty_first_copy_is_unsupported() -> io::Result<()> {
+        assert_eq!(linux::copy_loop(100, |_, _| 0)?, None);
+
+        let mut results = [60, 0].into_iter();
+        assert_eq!(
+            linux::copy_loop(100, |_, _| results.next().unwrap())?,
+            Some(60)
+        );
+
+        Ok(())
+    }
+}
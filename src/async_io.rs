@@ -0,0 +1,198 @@
+//! Async API on top of tokio, enabled with the `tokio` feature.
+//!
+//! Opening and extracting run the blocking implementations on tokio's
+//! blocking thread pool, so they parse and write exactly like [`VPK::open`]
+//! and [`VPK::extract_all`]. [`AsyncVPKFile`] reads entry data with
+//! positional reads through the VPK's shared archive handles.
+
+use std::fs::File;
+use std::future::Future;
+use std::io::{self, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll, ready};
+
+use tokio::io::{AsyncRead, AsyncSeek, ReadBuf};
+use tokio::task::JoinHandle;
+
+use crate::archive::read_at;
+use crate::error::{Result, VpkError};
+use crate::extract::ExtractOptions;
+use crate::file::{FileMetadata, seek_position};
+use crate::vpk::{OpenOptions, VPK};
+
+/// Largest archive read issued by one [`AsyncVPKFile::poll_read`] call
+const MAX_READ_SIZE: usize = 64 * 1024;
+
+/// Runs `work` on the blocking thread pool
+async fn blocking<T, F>(work: F) -> Result<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T> + Send + 'static,
+{
+    tokio::task::spawn_blocking(work)
+        .await
+        .map_err(|e| VpkError::io("Blocking task failed", io::Error::other(e)))?
+}
+
+impl OpenOptions {
+    /// Opens the VPK at `path` with these options without blocking the runtime
+    pub async fn open_async<P: AsRef<Path>>(&self, path: P) -> Result<VPK> {
+        let options = self.clone();
+        let path = path.as_ref().to_path_buf();
+        blocking(move || options.open(path)).await
+    }
+}
+
+impl VPK {
+    /// Opens an existing VPK file without blocking the runtime
+    pub async fn open_async<P: AsRef<Path>>(path: P) -> Result<Self> {
+        OpenOptions::new().open_async(path).await
+    }
+
+    /// Extracts every entry below `output_dir` like [`VPK::extract_all`],
+    /// without blocking the runtime
+    pub async fn extract_all_async<P: Into<PathBuf>>(
+        self: Arc<Self>,
+        output_dir: P,
+        options: ExtractOptions,
+    ) -> Result<usize> {
+        let output_dir = output_dir.into();
+        blocking(move || self.extract_all(output_dir, &options)).await
+    }
+
+    /// Gets an async reader for a file in the VPK.
+    ///
+    /// This opens the entry's archive chunk if no reader has used it yet.
+    pub fn get_async_file(&self, path: &str) -> Result<AsyncVPKFile> {
+        let (filepath, metadata, archive) = self.get_file(path)?.into_parts();
+        Ok(AsyncVPKFile {
+            filepath,
+            metadata,
+            archive,
+            position: 0,
+            pending: None,
+        })
+    }
+}
+
+/// An async reader for a file inside a VPK, created by [`VPK::get_async_file`]
+pub struct AsyncVPKFile {
+    filepath: String,
+    metadata: FileMetadata,
+    archive: Option<Arc<File>>,
+    position: u64,
+    /// Archive read running on the blocking pool, started at `position`
+    pending: Option<JoinHandle<io::Result<Vec<u8>>>>,
+}
+
+impl AsyncVPKFile {
+    /// Gets the file path within the VPK
+    pub fn filepath(&self) -> &str {
+        &self.filepath
+    }
+
+    /// Gets the file metadata
+    pub fn metadata(&self) -> &FileMetadata {
+        &self.metadata
+    }
+
+    /// Gets the current position in the file
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    /// Gets the total file length
+    pub fn length(&self) -> u64 {
+        self.metadata.total_length()
+    }
+}
+
+impl AsyncRead for AsyncVPKFile {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+
+        if let Some(pending) = &mut this.pending {
+            let result = ready!(Pin::new(pending).poll(cx));
+            this.pending = None;
+            let data = result.map_err(io::Error::other)??;
+            // The tree promised more bytes than the archive holds
+            if data.is_empty() {
+                return Poll::Ready(Err(io::ErrorKind::UnexpectedEof.into()));
+            }
+            // A smaller buffer than the one the read started with only
+            // takes part of the data; the rest is read again next time
+            let len = data.len().min(buf.remaining());
+            buf.put_slice(&data[..len]);
+            this.position += len as u64;
+            return Poll::Ready(Ok(()));
+        }
+
+        let remaining = this.length().saturating_sub(this.position);
+        if remaining == 0 || buf.remaining() == 0 {
+            return Poll::Ready(Ok(()));
+        }
+
+        // Preload bytes are in memory
+        let preload_length = this.metadata.preload_length as u64;
+        if this.position < preload_length {
+            let start = this.position as usize;
+            let end = (preload_length as usize).min(start + buf.remaining());
+            buf.put_slice(&this.metadata.preload[start..end]);
+            this.position = end as u64;
+            return Poll::Ready(Ok(()));
+        }
+
+        let Some(archive) = &this.archive else {
+            return Poll::Ready(Ok(()));
+        };
+        let archive = Arc::clone(archive);
+        let offset = this.metadata.archive_offset as u64 + this.position - preload_length;
+        let len = buf
+            .remaining()
+            .min(MAX_READ_SIZE)
+            .min(remaining.try_into().unwrap_or(usize::MAX));
+        this.pending = Some(tokio::task::spawn_blocking(move || {
+            let mut data = vec![0u8; len];
+            let read = read_at(&archive, &mut data, offset)?;
+            data.truncate(read);
+            Ok(data)
+        }));
+        // Poll the new task so the waker is registered
+        Pin::new(this).poll_read(cx, buf)
+    }
+}
+
+impl AsyncSeek for AsyncVPKFile {
+    fn start_seek(self: Pin<&mut Self>, position: SeekFrom) -> io::Result<()> {
+        let this = self.get_mut();
+        let new_position = seek_position(this.position, this.length(), position)?;
+
+        // An unfinished read has not moved the position yet, so drop it
+        if let Some(pending) = this.pending.take() {
+            pending.abort();
+        }
+        this.position = new_position;
+        Ok(())
+    }
+
+    fn poll_complete(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<u64>> {
+        Poll::Ready(Ok(self.position))
+    }
+}
+
+impl std::fmt::Debug for AsyncVPKFile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AsyncVPKFile")
+            .field("filepath", &self.filepath)
+            .field("position", &self.position)
+            .field("length", &self.length())
+            .field("metadata", &self.metadata)
+            .finish()
+    }
+}
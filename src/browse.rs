@@ -0,0 +1,252 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::ops::Bound;
+
+use crate::entry::Entry;
+use crate::error::{Result, VpkError};
+use crate::file::FileMetadata;
+use crate::utils::normalize_lookup_path;
+use crate::vpk::{PathLookup, VPK};
+
+/// Directory structure of a VPK, built from the tree on first use
+#[derive(Debug, Default)]
+pub(crate) struct DirIndex {
+    /// Directory path (`""` for the root) -> its children
+    dirs: BTreeMap<String, DirNode>,
+    /// Extension -> paths of the files with that extension, sorted
+    extensions: BTreeMap<String, Vec<String>>,
+    /// Normalized directory path -> the directory paths it matches, sorted
+    normalized: HashMap<String, Vec<String>>,
+}
+
+#[derive(Debug, Default)]
+struct DirNode {
+    /// Full paths of the child directories
+    dirs: BTreeSet<String>,
+    /// Full paths of the files directly in this directory, sorted
+    files: Vec<String>,
+}
+
+impl DirIndex {
+    pub(crate) fn build(tree: &HashMap<String, FileMetadata>) -> Self {
+        let mut index = DirIndex::default();
+        index.dirs.insert(String::new(), DirNode::default());
+
+        for path in tree.keys() {
+            let entry = Entry::new(path, &tree[path]);
+            index
+                .extensions
+                .entry(entry.extension().to_string())
+                .or_default()
+                .push(path.clone());
+
+            let mut dir = entry.directory();
+            index
+                .dirs
+                .entry(dir.to_string())
+                .or_default()
+                .files
+                .push(path.clone());
+
+            // Register every ancestor, so directories holding only
+            // subdirectories can be listed too
+            while !dir.is_empty() {
+                let parent = dir.rsplit_once('/').map_or("", |(parent, _)| parent);
+                let node = index.dirs.entry(parent.to_string()).or_default();
+                if !node.dirs.insert(dir.to_string()) {
+                    break;
+                }
+                dir = parent;
+            }
+        }
+
+        for node in index.dirs.values_mut() {
+            node.files.sort();
+        }
+        for paths in index.extensions.values_mut() {
+            paths.sort();
+        }
+        for dir in index.dirs.keys() {
+            index
+                .normalized
+                .entry(normalize_lookup_path(dir))
+                .or_default()
+                .push(dir.clone());
+        }
+
+        index
+    }
+}
+
+/// Returns true if `dir` is `prefix` or one of its subdirectories
+fn is_within(dir: &str, prefix: &str) -> bool {
+    prefix.is_empty()
+        || dir
+            .strip_prefix(prefix)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+}
+
+/// A child of a directory returned by [`VPK::read_dir`]
+#[derive(Debug, Clone, Copy)]
+pub enum DirEntry<'a> {
+    /// A subdirectory, with its full path
+    Directory(&'a str),
+    /// A file
+    File(Entry<'a>),
+}
+
+impl<'a> DirEntry<'a> {
+    /// Full path of the directory or file
+    pub fn path(&self) -> &'a str {
+        match self {
+            DirEntry::Directory(path) => path,
+            DirEntry::File(entry) => entry.path(),
+        }
+    }
+
+    /// Last component of the path
+    pub fn name(&self) -> &'a str {
+        let path = self.path();
+        path.rsplit_once('/').map_or(path, |(_, name)| name)
+    }
+
+    /// Returns true for subdirectories
+    pub fn is_dir(&self) -> bool {
+        matches!(self, DirEntry::Directory(_))
+    }
+}
+
+impl VPK {
+    /// Lists the subdirectories and files directly inside a directory.
+    ///
+    /// Use `""` or `"/"` for the root. Subdirectories come first, each group
+    /// sorted by path. In [`PathLookup::Normalized`] mode the directory is
+    /// matched ignoring case and separators.
+    pub fn read_dir(&self, path: &str) -> Result<Vec<DirEntry<'_>>> {
+        let tree = self.tree()?;
+        let index = self.dir_index()?;
+        let node = &index.dirs[self.resolve_dir(index, path)?];
+
+        let dirs = node.dirs.iter().map(|dir| DirEntry::Directory(dir));
+        let files = node.files.iter().map(|file| {
+            let (path, metadata) = tree
+                .get_key_value(file)
+                .expect("indexed path is in the tree");
+            DirEntry::File(Entry::new(path, metadata))
+        });
+        Ok(dirs.chain(files).collect())
+    }
+
+    /// Iterates over every file below a directory, recursively.
+    ///
+    /// The prefix is resolved like the path given to [`VPK::read_dir`], and
+    /// covers its subdirectories only, so `materials` covers
+    /// `materials/foo.vmt` but not `materials_old/foo.vmt`. Files are yielded
+    /// directory by directory, sorted within each one.
+    pub fn walk(&self, prefix: &str) -> Result<impl Iterator<Item = Entry<'_>>> {
+        let tree = self.tree()?;
+        let index = self.dir_index()?;
+        let prefix = self.resolve_dir(index, prefix)?;
+
+        Ok(index
+            .dirs
+            .range::<str, _>((Bound::Included(prefix), Bound::Unbounded))
+            .take_while(move |(dir, _)| dir.starts_with(prefix))
+            .filter(move |(dir, _)| is_within(dir, prefix))
+            .flat_map(|(_, node)| &node.files)
+            .map(move |file| {
+                let (path, metadata) = tree
+                    .get_key_value(file)
+                    .expect("indexed path is in the tree");
+                Entry::new(path, metadata)
+            }))
+    }
+
+    /// Iterates over every directory that holds files, directly or in a
+    /// subdirectory, sorted by path. The root is not included.
+    pub fn directories(&self) -> Result<impl Iterator<Item = &str>> {
+        Ok(self
+            .dir_index()?
+            .dirs
+            .keys()
+            .filter(|dir| !dir.is_empty())
+            .map(String::as_str))
+    }
+
+    /// Iterates over the distinct file extensions, sorted, without the leading dot
+    pub fn extensions(&self) -> Result<impl Iterator<Item = &str>> {
+        Ok(self.dir_index()?.extensions.keys().map(String::as_str))
+    }
+
+    /// Iterates over the files with an extension, sorted by path.
+    ///
+    /// The extension may be given with or without the leading dot. In
+    /// [`PathLookup::Normalized`] mode it is matched ignoring ASCII case.
+    pub fn files_with_extension(&self, ext: &str) -> Result<impl Iterator<Item = Entry<'_>>> {
+        let ext = ext.strip_prefix('.').unwrap_or(ext).to_string();
+        let tree = self.tree()?;
+        let normalized = self.path_lookup() == PathLookup::Normalized;
+
+        Ok(self
+            .dir_index()?
+            .extensions
+            .iter()
+            .filter(move |(key, _)| {
+                if normalized {
+                    key.eq_ignore_ascii_case(&ext)
+                } else {
+                    **key == ext
+                }
+            })
+            .flat_map(|(_, paths)| paths)
+            .map(move |file| {
+                let (path, metadata) = tree
+                    .get_key_value(file)
+                    .expect("indexed path is in the tree");
+                Entry::new(path, metadata)
+            }))
+    }
+
+    /// Resolves a directory path to the one stored in the index.
+    ///
+    /// An exact match always wins. In [`PathLookup::Normalized`] mode an
+    /// error is returned if several directories differ only in case.
+    fn resolve_dir<'a>(&self, index: &'a DirIndex, path: &str) -> Result<&'a str> {
+        let dir = path.replace('\\', "/");
+        if let Some((key, _)) = index.dirs.get_key_value(dir.trim_matches('/')) {
+            return Ok(key);
+        }
+
+        if self.path_lookup() == PathLookup::Exact {
+            return Err(VpkError::DirectoryNotFound(path.to_string()));
+        }
+
+        let candidates = index
+            .normalized
+            .get(&normalize_lookup_path(&dir))
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+
+        match candidates {
+            [] => Err(VpkError::DirectoryNotFound(path.to_string())),
+            [key] => Ok(key),
+            _ => Err(VpkError::AmbiguousPath {
+                path: path.to_string(),
+                candidates: candidates.to_vec(),
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_within() {
+        assert!(is_within("materials", ""));
+        assert!(is_within("materials", "materials"));
+        assert!(is_within("materials/models", "materials"));
+        assert!(!is_within("materials_old", "materials"));
+        assert!(!is_within("", "materials"));
+    }
+}
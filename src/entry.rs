@@ -0,0 +1,191 @@
+use std::collections::hash_map;
+
+use crate::error::{Result, VpkError};
+use crate::file::FileMetadata;
+use crate::utils::EMBEDDED_ARCHIVE_INDEX;
+use crate::vpk::VPK;
+
+/// Where the bytes of an entry that follow its preload data are stored
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntryLocation {
+    /// In the directory file itself, at an absolute offset
+    Embedded { offset: u64 },
+    /// In archive chunk `index` (`name_NNN.vpk`), at an offset within that chunk
+    Archive { index: u16, offset: u64 },
+    /// The entry has no data beyond its preload bytes
+    PreloadOnly,
+}
+
+/// A read-only view of one entry in a VPK
+#[derive(Debug, Clone, Copy)]
+pub struct Entry<'a> {
+    path: &'a str,
+    metadata: &'a FileMetadata,
+}
+
+impl<'a> Entry<'a> {
+    pub(crate) fn new(path: &'a str, metadata: &'a FileMetadata) -> Self {
+        Entry { path, metadata }
+    }
+
+    /// Full path of the entry, e.g. `materials/models/foo.vmt`
+    pub fn path(&self) -> &'a str {
+        self.path
+    }
+
+    /// Raw bytes of the path as stored in the tree
+    pub fn raw_path(&self) -> &'a [u8] {
+        self.metadata
+            .raw_path
+            .as_deref()
+            .unwrap_or(self.path.as_bytes())
+    }
+
+    /// Directory part of the path, empty for files in the root
+    pub fn directory(&self) -> &'a str {
+        self.path.rsplit_once('/').map_or("", |(dir, _)| dir)
+    }
+
+    /// File name including the extension
+    pub fn file_name(&self) -> &'a str {
+        self.path
+            .rsplit_once('/')
+            .map_or(self.path, |(_, name)| name)
+    }
+
+    /// Extension without the leading dot
+    pub fn extension(&self) -> &'a str {
+        self.file_name().rsplit_once('.').map_or("", |(_, ext)| ext)
+    }
+
+    /// CRC32 of the whole file as stored in the tree
+    pub fn crc32(&self) -> u32 {
+        self.metadata.crc32
+    }
+
+    /// Total size of the file in bytes
+    pub fn size(&self) -> u64 {
+        self.metadata.total_length()
+    }
+
+    /// Number of bytes stored inline in the tree
+    pub fn preload_length(&self) -> u16 {
+        self.metadata.preload_length
+    }
+
+    /// Bytes stored inline in the tree
+    pub fn preload(&self) -> &'a [u8] {
+        &self.metadata.preload[..self.metadata.preload_length as usize]
+    }
+
+    /// Where the rest of the data is stored
+    pub fn location(&self) -> EntryLocation {
+        if self.metadata.file_length == 0 {
+            EntryLocation::PreloadOnly
+        } else if self.metadata.archive_index == EMBEDDED_ARCHIVE_INDEX {
+            EntryLocation::Embedded {
+                offset: self.metadata.archive_offset as u64,
+            }
+        } else {
+            EntryLocation::Archive {
+                index: self.metadata.archive_index,
+                offset: self.metadata.archive_offset as u64,
+            }
+        }
+    }
+
+    /// Raw metadata of the entry
+    pub fn metadata(&self) -> &'a FileMetadata {
+        self.metadata
+    }
+}
+
+/// Iterator over the entries of a VPK, in arbitrary order
+#[derive(Debug, Clone)]
+pub struct Entries<'a> {
+    inner: Option<hash_map::Iter<'a, String, FileMetadata>>,
+}
+
+impl<'a> Iterator for Entries<'a> {
+    type Item = Entry<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (path, metadata) = self.inner.as_mut()?.next()?;
+        Some(Entry::new(path, metadata))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner
+            .as_ref()
+            .map_or((0, Some(0)), |inner| inner.size_hint())
+    }
+}
+
+impl ExactSizeIterator for Entries<'_> {}
+
+impl VPK {
+    /// Iterates over all entries without opening any archive files.
+    ///
+    /// For lazily opened VPKs whose tree fails to parse this yields nothing,
+    /// like [`VPK::file_paths`]; call [`VPK::index`] first to get the error.
+    pub fn entries(&self) -> Entries<'_> {
+        Entries {
+            inner: self.tree().ok().map(|tree| tree.iter()),
+        }
+    }
+
+    /// Gets the entry for a path, resolved according to the path lookup mode
+    pub fn entry(&self, path: &str) -> Result<Entry<'_>> {
+        let (path, metadata) = self
+            .tree()?
+            .get_key_value(self.resolve_path(path)?)
+            .ok_or_else(|| VpkError::FileNotFound(path.to_string()))?;
+        Ok(Entry::new(path, metadata))
+    }
+}
+
+/// Iterates over all entries, like [`VPK::entries`]
+impl<'a> IntoIterator for &'a VPK {
+    type Item = Entry<'a>;
+    type IntoIter = Entries<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entry_accessors() {
+        let metadata = FileMetadata {
+            preload: vec![1, 2, 3],
+            crc32: 0x12345678,
+            preload_length: 3,
+            archive_index: 2,
+            archive_offset: 100,
+            file_length: 50,
+            raw_path: None,
+        };
+        let entry = Entry::new("materials/models/foo.vmt", &metadata);
+
+        assert_eq!(entry.directory(), "materials/models");
+        assert_eq!(entry.file_name(), "foo.vmt");
+        assert_eq!(entry.extension(), "vmt");
+        assert_eq!(entry.size(), 53);
+        assert_eq!(entry.preload(), &[1, 2, 3]);
+        assert_eq!(
+            entry.location(),
+            EntryLocation::Archive {
+                index: 2,
+                offset: 100
+            }
+        );
+
+        let root = Entry::new("readme.txt", &metadata);
+        assert_eq!(root.directory(), "");
+        assert_eq!(root.file_name(), "readme.txt");
+    }
+}
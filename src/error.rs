@@ -0,0 +1,165 @@
+use std::io;
+use std::path::PathBuf;
+use std::str::Utf8Error;
+
+/// Errors returned by the VPK library
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum VpkError {
+    /// An I/O operation failed; `context` describes what was being done and to which path
+    #[error("{context}")]
+    Io {
+        context: String,
+        #[source]
+        source: io::Error,
+    },
+
+    /// No entry matches the requested path
+    #[error("File not found: {0}")]
+    FileNotFound(String),
+
+    /// No directory in the VPK matches the requested path
+    #[error("Directory not found: {0}")]
+    DirectoryNotFound(String),
+
+    /// A normalized lookup matched several entries that differ only in case
+    #[error("Ambiguous path {path}: matches {}", candidates.join(", "))]
+    AmbiguousPath {
+        path: String,
+        candidates: Vec<String>,
+    },
+
+    /// The file does not start with the VPK magic number
+    #[error("Invalid VPK signature: 0x{found:08x}")]
+    InvalidSignature { found: u32 },
+
+    /// The header declares a version this library cannot read
+    #[error("Unsupported VPK version: {0}")]
+    UnsupportedVersion(u32),
+
+    /// The file tree ends before the structure it describes is complete
+    #[error("Truncated file tree at offset {offset}")]
+    TruncatedTree { offset: u64 },
+
+    /// An entry's metadata is not followed by the `0xffff` terminator
+    #[error("Invalid metadata suffix 0x{suffix:04x} at offset {offset}")]
+    InvalidMetadataSuffix { offset: u64, suffix: u16 },
+
+    /// A string is not valid UTF-8; `what` names the string and where it was read from
+    #[error("Invalid UTF-8 in {what}")]
+    InvalidUtf8 {
+        what: String,
+        #[source]
+        source: Utf8Error,
+    },
+
+    /// Entry names with different raw bytes decode to the same path under
+    /// the chosen [`NameEncoding`](crate::utils::NameEncoding)
+    #[error("Several entry names decode to {path}; open with another name encoding")]
+    NameCollision { path: String },
+
+    /// A parsed value exceeds one of the configured [`Limits`](crate::vpk::Limits)
+    #[error("{what} of {value} exceeds the limit of {limit}")]
+    LimitExceeded {
+        what: String,
+        value: u64,
+        limit: u64,
+    },
+
+    /// A region declared by the VPK extends past the data that holds it
+    #[error("{what} ends at offset {end}, past the end of its data ({size} bytes)")]
+    OutOfBounds { what: String, end: u64, size: u64 },
+
+    /// The archive chunk holding an entry's data could not be opened
+    #[error("Missing archive chunk {index}: {}", path.display())]
+    MissingArchive {
+        index: u16,
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+
+    /// An entry path would write outside the output directory if extracted as is
+    #[error("Unsafe entry path {path:?}: {reason}")]
+    UnsafePath { path: String, reason: &'static str },
+
+    /// The operation needs a VPK that has been saved to or opened from disk
+    #[error("Cannot {0} an unsaved VPK")]
+    NotSaved(&'static str),
+
+    /// A path expected to be a directory is not one
+    #[error("Path is not a directory: {}", .0.display())]
+    NotADirectory(PathBuf),
+
+    /// VPK entries are grouped by extension, so every file needs one
+    #[error("Files without an extension are not supported: {0}")]
+    MissingExtension(String),
+
+    /// A glob or regular expression failed to compile
+    #[error("Invalid pattern {pattern:?}: {reason}")]
+    InvalidPattern { pattern: String, reason: String },
+
+    /// Two source files map to the same archive path
+    #[error("Conflicting sources for {path}: {} and {}", first.display(), second.display())]
+    PathConflict {
+        path: String,
+        first: PathBuf,
+        second: PathBuf,
+    },
+
+    /// A pack manifest could not be parsed or describes an invalid archive
+    #[error("Invalid manifest: {0}")]
+    InvalidManifest(String),
+
+    /// A [`Progress`](crate::progress::Progress) observer cancelled the operation
+    #[error("Operation cancelled")]
+    Cancelled,
+
+    /// The operation is not supported for this archive
+    #[error("{0}")]
+    Unsupported(&'static str),
+}
+
+impl VpkError {
+    /// Wraps an I/O error with a description of the failed operation
+    pub(crate) fn io(context: impl Into<String>, source: io::Error) -> Self {
+        VpkError::Io {
+            context: context.into(),
+            source,
+        }
+    }
+
+    /// Describes a bare I/O error, keeping any context already attached
+    pub(crate) fn or_io_context<C: Into<String>>(self, context: impl FnOnce() -> C) -> Self {
+        match self {
+            VpkError::Io {
+                context: existing,
+                source,
+            } if existing == DEFAULT_IO_CONTEXT => VpkError::io(context(), source),
+            other => other,
+        }
+    }
+}
+
+/// Context of I/O errors converted with `?` and no description
+const DEFAULT_IO_CONTEXT: &str = "I/O error";
+
+impl From<io::Error> for VpkError {
+    fn from(source: io::Error) -> Self {
+        VpkError::io(DEFAULT_IO_CONTEXT, source)
+    }
+}
+
+/// Result type used throughout the library
+pub type Result<T, E = VpkError> = std::result::Result<T, E>;
+
+/// Attaches context to I/O results, turning them into [`VpkError::Io`]
+pub(crate) trait IoResultExt<T> {
+    fn io_context<C: Into<String>>(self, context: impl FnOnce() -> C) -> Result<T>;
+}
+
+impl<T> IoResultExt<T> for io::Result<T> {
+    fn io_context<C: Into<String>>(self, context: impl FnOnce() -> C) -> Result<T> {
+        self.map_err(|source| VpkError::io(context(), source))
+    }
+}
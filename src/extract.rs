@@ -0,0 +1,438 @@
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::archive::copy_range;
+use crate::entry::Entry;
+use crate::error::{IoResultExt, Result, VpkError};
+use crate::parallel::{read_order, run_batches, split_batches, thread_count};
+use crate::progress::{Operation, Progress, ProgressTracker, SharedProgress};
+use crate::utils::display_name;
+use crate::vpk::VPK;
+
+/// Device names that Windows reserves in every directory, with or without an extension
+const WINDOWS_RESERVED_NAMES: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Characters that are not allowed in Windows file names
+const WINDOWS_RESERVED_CHARS: &[u8] = b"<>:\"|?*";
+
+/// What to do with entry paths that would escape the output directory
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum UnsafePathPolicy {
+    /// Fail with [`VpkError::UnsafePath`]
+    #[default]
+    Reject,
+    /// Strip roots and `..` components and replace invalid characters.
+    ///
+    /// When several entries end up at the same path, only the one whose
+    /// entry path sorts first is extracted. Paths differing only in case
+    /// count as the same on Windows and macOS, or with
+    /// [`ExtractOptions::windows_names`].
+    Sanitize,
+}
+
+/// Options controlling how entries are written to disk
+#[derive(Debug, Clone)]
+pub struct ExtractOptions {
+    unsafe_paths: UnsafePathPolicy,
+    windows_names: bool,
+    jobs: usize,
+    progress: SharedProgress,
+}
+
+// Not derivable on Windows, where `cfg!(windows)` is true
+#[allow(clippy::derivable_impls)]
+impl Default for ExtractOptions {
+    fn default() -> Self {
+        ExtractOptions {
+            unsafe_paths: UnsafePathPolicy::default(),
+            windows_names: cfg!(windows),
+            jobs: 0,
+            progress: SharedProgress::default(),
+        }
+    }
+}
+
+impl ExtractOptions {
+    /// Creates the default options: unsafe paths are rejected, and Windows
+    /// reserved names are checked when running on Windows
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets what to do with absolute paths, `..` components and NUL bytes
+    pub fn unsafe_paths(mut self, policy: UnsafePathPolicy) -> Self {
+        self.unsafe_paths = policy;
+        self
+    }
+
+    /// Also treats Windows device names such as `CON` and characters such as
+    /// `:` or `?` as unsafe
+    pub fn windows_names(mut self, check: bool) -> Self {
+        self.windows_names = check;
+        self
+    }
+
+    /// Sets the number of threads used by [`VPK::extract_all`]; `0`, the
+    /// default, uses one per available CPU
+    pub fn jobs(mut self, jobs: usize) -> Self {
+        self.jobs = jobs;
+        self
+    }
+
+    /// Reports each extracted entry to `progress`, which can also cancel
+    /// the extraction
+    pub fn progress(mut self, progress: Arc<dyn Progress>) -> Self {
+        self.progress = SharedProgress(Some(progress));
+        self
+    }
+}
+
+/// Turns a VPK entry path into a relative path that stays inside the output directory.
+///
+/// Both `/` and `\` are treated as separators, and empty and `.` components are
+/// dropped. Absolute paths, `..` components and NUL bytes are rejected or
+/// sanitized according to `options`.
+pub fn sanitize_entry_path(entry: &str, options: &ExtractOptions) -> Result<PathBuf> {
+    sanitize_raw_entry_path(entry.as_bytes(), options)
+}
+
+/// Like [`sanitize_entry_path`], but for the raw bytes of an entry name.
+///
+/// On Unix the resulting path uses the exact bytes, so names that are not
+/// valid UTF-8 are extracted unchanged.
+pub fn sanitize_raw_entry_path(entry: &[u8], options: &ExtractOptions) -> Result<PathBuf> {
+    let sanitize = options.unsafe_paths == UnsafePathPolicy::Sanitize;
+    let unsafe_path = |reason: &'static str| VpkError::UnsafePath {
+        path: display_name(entry).into_owned(),
+        reason,
+    };
+
+    if entry.contains(&0) && !sanitize {
+        return Err(unsafe_path("contains a NUL byte"));
+    }
+
+    let has_drive = entry.len() >= 2 && entry[0].is_ascii_alphabetic() && entry[1] == b':';
+    if (entry.starts_with(b"/") || entry.starts_with(b"\\") || has_drive) && !sanitize {
+        return Err(unsafe_path("is an absolute path"));
+    }
+    let relative = if has_drive { &entry[2..] } else { entry };
+
+    let mut path = PathBuf::new();
+    for component in relative.split(|&b| b == b'/' || b == b'\\') {
+        match component {
+            b"" | b"." => continue,
+            b".." if sanitize => continue,
+            b".." => return Err(unsafe_path("contains a parent directory component")),
+            _ => {}
+        }
+
+        let mut component = replace_bytes(component, &[0]);
+        if options.windows_names {
+            component = check_windows_name(component, sanitize).map_err(unsafe_path)?;
+        }
+        path.push(component_to_os_string(component));
+    }
+
+    if path.as_os_str().is_empty() {
+        return Err(unsafe_path("has no file name"));
+    }
+
+    Ok(path)
+}
+
+/// Replaces every byte found in `bytes` with `_`
+fn replace_bytes(component: &[u8], bytes: &[u8]) -> Vec<u8> {
+    component
+        .iter()
+        .map(|b| if bytes.contains(b) { b'_' } else { *b })
+        .collect()
+}
+
+/// Converts a path component to an `OsString`, keeping raw bytes where the platform allows it
+fn component_to_os_string(component: Vec<u8>) -> std::ffi::OsString {
+    #[cfg(unix)]
+    {
+        std::os::unix::ffi::OsStringExt::from_vec(component)
+    }
+    #[cfg(not(unix))]
+    {
+        display_name(&component).into_owned().into()
+    }
+}
+
+/// Checks one path component against Windows naming rules, fixing it up when sanitizing
+fn check_windows_name(component: Vec<u8>, sanitize: bool) -> Result<Vec<u8>, &'static str> {
+    let mut component = if component.iter().any(|b| WINDOWS_RESERVED_CHARS.contains(b)) {
+        if !sanitize {
+            return Err("contains a character reserved on Windows");
+        }
+        replace_bytes(&component, WINDOWS_RESERVED_CHARS)
+    } else {
+        component
+    };
+
+    // Windows silently strips trailing dots and spaces
+    if component.ends_with(b".") || component.ends_with(b" ") {
+        if !sanitize {
+            return Err("ends with a dot or space");
+        }
+        while component.ends_with(b".") || component.ends_with(b" ") {
+            component.pop();
+        }
+        component.push(b'_');
+    }
+
+    let stem = component.split(|&b| b == b'.').next().unwrap_or_default();
+    if WINDOWS_RESERVED_NAMES
+        .iter()
+        .any(|name| stem.eq_ignore_ascii_case(name.as_bytes()))
+    {
+        if !sanitize {
+            return Err("is a reserved Windows device name");
+        }
+        component.insert(0, b'_');
+    }
+
+    Ok(component)
+}
+
+/// Joins a VPK entry path onto `root`, guaranteeing the result stays inside it
+pub fn safe_join(root: &Path, entry: &str, options: &ExtractOptions) -> Result<PathBuf> {
+    Ok(root.join(sanitize_entry_path(entry, options)?))
+}
+
+impl VPK {
+    /// Extracts one entry below `output_dir`, creating parent directories as
+    /// needed, and returns the path that was written.
+    ///
+    /// The raw entry path is checked with [`sanitize_raw_entry_path`], so
+    /// entries such as `../../.bashrc` cannot write outside `output_dir`.
+    pub fn extract_file<P: AsRef<Path>>(
+        &self,
+        path: &str,
+        output_dir: P,
+        options: &ExtractOptions,
+    ) -> Result<PathBuf> {
+        let mut file = self.get_file(path)?;
+        let raw_path = self
+            .raw_path(file.filepath())
+            .unwrap_or(file.filepath().as_bytes());
+        let output_path = output_dir
+            .as_ref()
+            .join(sanitize_raw_entry_path(raw_path, options)?);
+
+        if let Some(parent) = output_path.parent() {
+            fs::create_dir_all(parent).io_context(|| {
+                format!("Failed to create parent directory: {}", parent.display())
+            })?;
+        }
+
+        file.save(&output_path)?;
+        Ok(output_path)
+    }
+
+    /// Extracts every entry below `output_dir` in parallel and returns the
+    /// number of files written.
+    ///
+    /// See [`VPK::extract_entries`].
+    pub fn extract_all<P: AsRef<Path>>(
+        &self,
+        output_dir: P,
+        options: &ExtractOptions,
+    ) -> Result<usize> {
+        self.index()?;
+        let entries: Vec<_> = self.entries().collect();
+        self.extract_entries(&entries, output_dir, options)
+    }
+
+    /// Extracts the given entries below `output_dir` in parallel and returns
+    /// the number of files written.
+    ///
+    /// Every entry path is checked before anything is written, including
+    /// entries that would be written to the same file, which are handled
+    /// according to [`ExtractOptions::unsafe_paths`]. Entries are
+    /// then sorted by archive chunk and offset and split into batches that
+    /// each read one chunk sequentially through a single handle, spread over
+    /// [`ExtractOptions::jobs`] threads. The first error, including a
+    /// cancellation by [`ExtractOptions::progress`], stops the remaining
+    /// batches and is returned.
+    pub fn extract_entries<P: AsRef<Path>>(
+        &self,
+        entries: &[Entry<'_>],
+        output_dir: P,
+        options: &ExtractOptions,
+    ) -> Result<usize> {
+        if self.path().is_none() {
+            return Err(VpkError::NotSaved("extract from"));
+        }
+        let output_dir = output_dir.as_ref();
+
+        // Parallel batches must never write the same file, which on
+        // case-insensitive file systems includes paths differing in case
+        let case_insensitive = options.windows_names || cfg!(any(windows, target_os = "macos"));
+        let mut jobs = entries
+            .iter()
+            .map(|entry| {
+                let target = output_dir.join(sanitize_raw_entry_path(entry.raw_path(), options)?);
+                let bytes = target.as_os_str().as_encoded_bytes();
+                let key = if case_insensitive {
+                    String::from_utf8_lossy(bytes).to_lowercase().into_bytes()
+                } else {
+                    bytes.to_vec()
+                };
+                Ok((key, (*entry, target)))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        jobs.sort_by(|(a_key, (a, _)), (b_key, (b, _))| {
+            a_key
+                .cmp(b_key)
+                .then_with(|| a.raw_path().cmp(b.raw_path()))
+        });
+        if options.unsafe_paths == UnsafePathPolicy::Reject
+            && let Some(pair) = jobs.windows(2).find(|pair| pair[0].0 == pair[1].0)
+        {
+            return Err(VpkError::UnsafePath {
+                path: display_name(pair[1].1.0.raw_path()).into_owned(),
+                reason: "extracts to the same file as another entry",
+            });
+        }
+        jobs.dedup_by(|(key, _), (kept, _)| key == kept);
+        let mut jobs: Vec<_> = jobs.into_iter().map(|(_, job)| job).collect();
+        jobs.sort_by_key(|(entry, _)| read_order(entry));
+
+        let progress = ProgressTracker::new(
+            options.progress.0.as_deref(),
+            Operation::Extract,
+            jobs.len(),
+            jobs.iter().map(|(entry, _)| entry.size()).sum(),
+        );
+        let threads = thread_count(options.jobs, jobs.len());
+        let batches = split_batches(&jobs, threads, |(entry, _)| entry.metadata().archive_index);
+        run_batches(&batches, threads, |batch| {
+            extract_batch(self, batch, &progress)
+        })?;
+        Ok(jobs.len())
+    }
+}
+
+/// Extracts a batch of entries whose data is in the same archive chunk
+fn extract_batch(
+    vpk: &VPK,
+    batch: &[(Entry<'_>, PathBuf)],
+    progress: &ProgressTracker<'_>,
+) -> Result<()> {
+    let mut archive: Option<Arc<File>> = None;
+
+    for (entry, output_path) in batch {
+        if let Some(parent) = output_path.parent() {
+            fs::create_dir_all(parent).io_context(|| {
+                format!("Failed to create parent directory: {}", parent.display())
+            })?;
+        }
+        let mut output = File::create(output_path)
+            .io_context(|| format!("Failed to create output file: {}", output_path.display()))?;
+        let write_context = || format!("Failed to write to output file: {}", output_path.display());
+        output
+            .write_all(entry.preload())
+            .io_context(write_context)?;
+
+        let metadata = entry.metadata();
+        if metadata.file_length == 0 {
+            progress.file_done(entry.path(), entry.size())?;
+            continue;
+        }
+
+        let archive = match &archive {
+            Some(archive) => archive,
+            None => archive.insert(vpk.archive(metadata.archive_index)?),
+        };
+
+        let read_context = || format!("Failed to read {}", entry.path());
+        let copied = copy_range(
+            archive,
+            metadata.archive_offset as u64,
+            metadata.file_length as u64,
+            &mut output,
+        )
+        .io_context(write_context)?;
+        if copied < metadata.file_length as u64 {
+            return Err(VpkError::io(
+                read_context(),
+                io::ErrorKind::UnexpectedEof.into(),
+            ));
+        }
+        progress.file_done(entry.path(), entry.size())?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sanitize_entry_path() -> Result<()> {
+        let reject = ExtractOptions::new().windows_names(true);
+        let sanitize = reject.clone().unsafe_paths(UnsafePathPolicy::Sanitize);
+
+        assert_eq!(
+            sanitize_entry_path("materials\\models/./foo.vmt", &reject)?,
+            Path::new("materials/models/foo.vmt")
+        );
+
+        for entry in [
+            "../../.bashrc",
+            "/etc/passwd",
+            "C:\\Windows\\win.ini",
+            "a\0b.txt",
+            "con.txt",
+            "dir/what?.txt",
+            "..",
+        ] {
+            assert!(
+                matches!(
+                    sanitize_entry_path(entry, &reject),
+                    Err(VpkError::UnsafePath { .. })
+                ),
+                "{entry} should be rejected"
+            );
+        }
+
+        assert_eq!(
+            sanitize_entry_path("../../.bashrc", &sanitize)?,
+            Path::new(".bashrc")
+        );
+        assert_eq!(
+            sanitize_entry_path("/etc/passwd", &sanitize)?,
+            Path::new("etc/passwd")
+        );
+        assert_eq!(
+            sanitize_entry_path("C:\\Windows\\win.ini", &sanitize)?,
+            Path::new("Windows/win.ini")
+        );
+        assert_eq!(
+            sanitize_entry_path("dir/CON.txt", &sanitize)?,
+            Path::new("dir/_CON.txt")
+        );
+        assert_eq!(
+            sanitize_entry_path("what?.txt", &sanitize)?,
+            Path::new("what_.txt")
+        );
+
+        // Device names are fine when Windows checks are off
+        let posix = ExtractOptions::new().windows_names(false);
+        assert_eq!(
+            sanitize_entry_path("con.txt", &posix)?,
+            Path::new("con.txt")
+        );
+
+        Ok(())
+    }
+}
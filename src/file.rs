@@ -1,8 +1,11 @@
-use anyhow::{Context, Result};
 use crc32fast::Hasher;
 use std::fs::File;
-use std::io::{BufReader, Read, Seek, SeekFrom, Write};
+use std::io::{BufRead, Read, Seek, SeekFrom, Write};
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::archive::{ArchiveRange, copy_range, open_archive, read_at};
+use crate::error::{IoResultExt, Result, VpkError};
 
 /// Metadata for a file stored in a VPK archive
 #[derive(Debug, Clone)]
@@ -13,61 +16,112 @@ pub struct FileMetadata {
     pub archive_index: u16,
     pub archive_offset: u32,
     pub file_length: u32,
+    /// Raw bytes of the entry path when they differ from the UTF-8 path,
+    /// i.e. when the name was decoded with a fallback [`NameEncoding`](crate::utils::NameEncoding)
+    pub raw_path: Option<Vec<u8>>,
 }
 
 impl FileMetadata {
     /// Total length of the file (preload + file data)
-    pub fn total_length(&self) -> u32 {
-        self.preload_length as u32 + self.file_length
+    pub fn total_length(&self) -> u64 {
+        self.preload_length as u64 + self.file_length as u64
     }
 }
 
-/// A file-like object for files inside VPK archives
+/// Size of the buffer used by [`BufRead`] over archive data
+const READ_BUFFER_SIZE: usize = 8 * 1024;
+
+/// A file-like object for files inside VPK archives.
+///
+/// Reads are positional, so cursors created by [`VPK::get_file`](crate::VPK::get_file)
+/// or [`VPKFile::try_clone`] share the VPK's archive handles and can be used
+/// from different threads.
+///
+/// [`BufRead`] serves preload bytes straight from memory and buffers archive
+/// data in 8 KiB blocks.
 #[allow(dead_code)]
 pub struct VPKFile {
     vpk_path: PathBuf,
     filepath: String,
     metadata: FileMetadata,
-    position: u32,
-    file_handle: Option<BufReader<File>>,
+    position: u64,
+    archive: Option<Arc<File>>,
+    /// Archive data for the file offsets starting at `buffer_start`
+    buffer: Vec<u8>,
+    buffer_start: u64,
 }
 
 impl VPKFile {
+    /// Creates a reader for an entry, opening its archive file
     pub fn new<P: AsRef<Path>>(
         vpk_path: P,
         filepath: String,
         metadata: FileMetadata,
     ) -> Result<Self> {
-        let vpk_path = vpk_path.as_ref().to_path_buf();
-
-        let file_handle = if metadata.file_length > 0 {
-            let actual_path = Self::resolve_archive_path(&vpk_path, metadata.archive_index)?;
-            let file = File::open(&actual_path).with_context(|| {
-                format!("Failed to open VPK archive: {}", actual_path.display())
-            })?;
-            Some(BufReader::new(file))
+        let vpk_path = vpk_path.as_ref();
+        let archive = if metadata.file_length > 0 {
+            Some(Arc::new(open_archive(vpk_path, metadata.archive_index)?))
         } else {
             None
         };
+        Ok(Self::with_archive(vpk_path, filepath, metadata, archive))
+    }
 
-        Ok(VPKFile {
-            vpk_path,
+    /// Creates a reader for an entry whose archive file is already open
+    pub(crate) fn with_archive(
+        vpk_path: &Path,
+        filepath: String,
+        metadata: FileMetadata,
+        archive: Option<Arc<File>>,
+    ) -> Self {
+        VPKFile {
+            vpk_path: vpk_path.to_path_buf(),
             filepath,
             metadata,
             position: 0,
-            file_handle,
+            archive,
+            buffer: Vec::new(),
+            buffer_start: 0,
+        }
+    }
+
+    /// Creates another cursor over the same entry, starting at the current
+    /// position.
+    ///
+    /// The new cursor shares the archive handle but moves independently,
+    /// unlike [`File::try_clone`] whose handles share one position.
+    pub fn try_clone(&self) -> Result<VPKFile> {
+        Ok(VPKFile {
+            vpk_path: self.vpk_path.clone(),
+            filepath: self.filepath.clone(),
+            metadata: self.metadata.clone(),
+            position: self.position,
+            archive: self.archive.clone(),
+            buffer: Vec::new(),
+            buffer_start: 0,
         })
     }
 
+    /// Splits the reader into its entry path, metadata and archive handle
+    #[cfg(feature = "tokio")]
+    pub(crate) fn into_parts(self) -> (String, FileMetadata, Option<Arc<File>>) {
+        (self.filepath, self.metadata, self.archive)
+    }
+
     /// Resolves the actual archive file path based on the archive index
-    fn resolve_archive_path(vpk_path: &Path, archive_index: u16) -> Result<PathBuf> {
+    pub(crate) fn resolve_archive_path(vpk_path: &Path, archive_index: u16) -> PathBuf {
         if archive_index == crate::utils::EMBEDDED_ARCHIVE_INDEX {
-            Ok(vpk_path.to_path_buf())
-        } else {
-            // Replace "dir." with the archive number, e.g., "pak01_001.vpk" -> "pak01_002.vpk"
-            let path_str = vpk_path.to_string_lossy();
-            let new_path = path_str.replace("dir.", &format!("{archive_index:03}."));
-            Ok(PathBuf::from(&new_path))
+            return vpk_path.to_path_buf();
+        }
+
+        // Only the file name changes, e.g. "pak01_dir.vpk" -> "pak01_002.vpk"
+        let name = vpk_path
+            .file_name()
+            .map(|name| name.to_string_lossy())
+            .unwrap_or_default();
+        match name.strip_suffix("_dir.vpk") {
+            Some(stem) => vpk_path.with_file_name(format!("{stem}_{archive_index:03}.vpk")),
+            None => vpk_path.to_path_buf(),
         }
     }
 
@@ -82,35 +136,105 @@ impl VPKFile {
     }
 
     /// Gets the current position in the file
-    pub fn position(&self) -> u32 {
+    pub fn position(&self) -> u64 {
         self.position
     }
 
     /// Gets the total file length
-    pub fn length(&self) -> u32 {
+    pub fn length(&self) -> u64 {
         self.metadata.total_length()
     }
 
-    /// Saves the entire file to the specified path
-    pub fn save<P: AsRef<Path>>(&mut self, output_path: P) -> Result<()> {
-        let current_pos = self.position;
-        self.seek(SeekFrom::Start(0))?;
+    /// Bytes of the buffered archive data at the current position
+    fn buffered(&self) -> &[u8] {
+        let buffer_end = self.buffer_start + self.buffer.len() as u64;
+        if (self.buffer_start..buffer_end).contains(&self.position) {
+            &self.buffer[(self.position - self.buffer_start) as usize..]
+        } else {
+            &[]
+        }
+    }
 
-        let mut output_file = File::create(output_path).context("Failed to create output file")?;
+    /// Reads into `buf` until it is full or the file ends, and returns the
+    /// number of bytes read.
+    ///
+    /// Preload bytes are copied from memory and the archive part is read with
+    /// as few positional reads as the OS allows. Fails with
+    /// [`UnexpectedEof`](std::io::ErrorKind::UnexpectedEof) if the archive is
+    /// shorter than the entry.
+    fn read_fully(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let to_read = buf
+            .len()
+            .min(self.length().saturating_sub(self.position) as usize);
+        let buf = &mut buf[..to_read];
+        let mut filled = 0;
+
+        let preload_length = self.metadata.preload_length as u64;
+        if self.position < preload_length {
+            let start = self.position as usize;
+            let count = to_read.min(preload_length as usize - start);
+            buf[..count].copy_from_slice(&self.metadata.preload[start..start + count]);
+            filled = count;
+            self.position += count as u64;
+        }
 
-        let mut buffer = vec![0u8; 8192];
-        loop {
-            let bytes_read = self.read(&mut buffer)?;
-            if bytes_read == 0 {
-                break;
+        if filled < to_read {
+            let Some(archive) = &self.archive else {
+                return Err(std::io::ErrorKind::UnexpectedEof.into());
+            };
+            let archive_position =
+                self.metadata.archive_offset as u64 + self.position - preload_length;
+            let mut data = ArchiveRange::new(archive, archive_position, (to_read - filled) as u64);
+            while filled < to_read {
+                match data.read(&mut buf[filled..]) {
+                    Ok(0) => return Err(std::io::ErrorKind::UnexpectedEof.into()),
+                    Ok(read) => {
+                        filled += read;
+                        self.position += read as u64;
+                    }
+                    Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+                    Err(e) => return Err(e),
+                }
+            }
+        }
+
+        Ok(filled)
+    }
+
+    /// Saves the entire file to the specified path.
+    ///
+    /// The part stored in the archive is copied file to file where the
+    /// platform allows it, see [`VPK::extract_all`](crate::VPK::extract_all).
+    pub fn save<P: AsRef<Path>>(&mut self, output_path: P) -> Result<()> {
+        let output_path = output_path.as_ref();
+        let mut output_file = File::create(output_path)
+            .io_context(|| format!("Failed to create output file: {}", output_path.display()))?;
+        let write_context = || format!("Failed to write to output file: {}", output_path.display());
+
+        let preload_length =
+            (self.metadata.preload_length as usize).min(self.metadata.preload.len());
+        output_file
+            .write_all(&self.metadata.preload[..preload_length])
+            .io_context(write_context)?;
+
+        if let Some(archive) = &self.archive
+            && self.metadata.file_length > 0
+        {
+            let copied = copy_range(
+                archive,
+                self.metadata.archive_offset as u64,
+                self.metadata.file_length as u64,
+                &mut output_file,
+            )
+            .io_context(write_context)?;
+            if copied < self.metadata.file_length as u64 {
+                return Err(VpkError::io(
+                    format!("Failed to read {}", self.filepath),
+                    std::io::ErrorKind::UnexpectedEof.into(),
+                ));
             }
-            output_file
-                .write_all(&buffer[..bytes_read])
-                .context("Failed to write to output file")?;
         }
 
-        // Restore position
-        self.position = current_pos;
         Ok(())
     }
 
@@ -123,7 +247,9 @@ impl VPKFile {
         let mut buffer = vec![0u8; 8192];
 
         loop {
-            let bytes_read = self.read(&mut buffer)?;
+            let bytes_read = self
+                .read(&mut buffer)
+                .io_context(|| format!("Failed to read {}", self.filepath))?;
             if bytes_read == 0 {
                 break;
             }
@@ -139,27 +265,23 @@ impl VPKFile {
     /// Reads all content into a Vec<u8>
     pub fn read_all(&mut self) -> Result<Vec<u8>> {
         let current_pos = self.position;
-        self.seek(SeekFrom::Start(0))?;
-
-        let mut buffer = Vec::with_capacity(self.length() as usize);
-        let mut temp_buffer = vec![0u8; 8192];
-
-        loop {
-            let bytes_read = self.read(&mut temp_buffer)?;
-            if bytes_read == 0 {
-                break;
-            }
-            buffer.extend_from_slice(&temp_buffer[..bytes_read]);
-        }
+        self.position = 0;
 
+        let mut buffer = Vec::new();
+        let result = self.read_to_end(&mut buffer);
         self.position = current_pos;
+        result.io_context(|| format!("Failed to read {}", self.filepath))?;
+
         Ok(buffer)
     }
 
     /// Reads the entire file as a UTF-8 string
     pub fn read_all_string(&mut self) -> Result<String> {
         let bytes = self.read_all()?;
-        String::from_utf8(bytes).context("File contains invalid UTF-8")
+        String::from_utf8(bytes).map_err(|e| VpkError::InvalidUtf8 {
+            what: format!("file {}", self.filepath),
+            source: e.utf8_error(),
+        })
     }
 }
 
@@ -169,65 +291,149 @@ impl Read for VPKFile {
             return Ok(0);
         }
 
+        // Use archive data already buffered by `fill_buf`
+        let buffered = self.buffered();
+        if !buffered.is_empty() {
+            let count = buffered.len().min(buf.len());
+            buf[..count].copy_from_slice(&buffered[..count]);
+            self.position += count as u64;
+            return Ok(count);
+        }
+
         let mut bytes_read = 0;
         let remaining_length = (self.length() - self.position) as usize;
         let to_read = buf.len().min(remaining_length);
 
         // Read from preload data first
-        if self.position < self.metadata.preload_length as u32 {
+        let preload_length = self.metadata.preload_length as u64;
+        if self.position < preload_length {
             let preload_start = self.position as usize;
-            let preload_end =
-                (self.metadata.preload_length as u32).min(self.position + to_read as u32) as usize;
+            let preload_end = (preload_length as usize).min(preload_start + to_read);
             let preload_bytes = preload_end - preload_start;
 
             buf[..preload_bytes]
                 .copy_from_slice(&self.metadata.preload[preload_start..preload_end]);
             bytes_read += preload_bytes;
-            self.position += preload_bytes as u32;
+            self.position += preload_bytes as u64;
         }
 
-        let length = self.length();
-
         // Read from archive file if there's still data to read and we have file data
-        if bytes_read < to_read && self.metadata.file_length > 0 && self.file_handle.is_some() {
-            if let Some(ref mut file_handle) = self.file_handle {
-                let archive_position = self.metadata.archive_offset + self.position;
+        if bytes_read < to_read
+            && self.metadata.file_length > 0
+            && let Some(archive) = &self.archive
+        {
+            // Archive data starts after the preload bytes
+            let archive_position =
+                self.metadata.archive_offset as u64 + self.position - preload_length;
+
+            let file_bytes_read =
+                read_at(archive, &mut buf[bytes_read..to_read], archive_position)?;
+            bytes_read += file_bytes_read;
+            self.position += file_bytes_read as u64;
+        }
 
-                file_handle
-                    .seek(SeekFrom::Start(archive_position as u64))
-                    .map_err(std::io::Error::other)?;
+        Ok(bytes_read)
+    }
 
-                let remaining = (length - self.position) as usize;
-                let to_read_from_file = (to_read - bytes_read).min(remaining);
+    fn read_exact(&mut self, buf: &mut [u8]) -> std::io::Result<()> {
+        if (buf.len() as u64) > self.length().saturating_sub(self.position) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::UnexpectedEof,
+                "failed to fill whole buffer",
+            ));
+        }
+        self.read_fully(buf).map(|_| ())
+    }
+
+    fn read_to_end(&mut self, buf: &mut Vec<u8>) -> std::io::Result<usize> {
+        let start = buf.len();
+        let remaining = self.length().saturating_sub(self.position) as usize;
+        buf.resize(start + remaining, 0);
 
-                let file_bytes_read =
-                    file_handle.read(&mut buf[bytes_read..bytes_read + to_read_from_file])?;
-                bytes_read += file_bytes_read;
-                self.position += file_bytes_read as u32;
+        let position = self.position;
+        match self.read_fully(&mut buf[start..]) {
+            Ok(read) => {
+                buf.truncate(start + read);
+                Ok(read)
+            }
+            Err(e) => {
+                // Keep the bytes that were read, like the default implementation
+                buf.truncate(start + (self.position - position) as usize);
+                Err(e)
             }
         }
+    }
+}
 
-        Ok(bytes_read)
+impl BufRead for VPKFile {
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        let length = self.length();
+        if self.position >= length {
+            return Ok(&[]);
+        }
+
+        // Preload bytes are already in memory
+        let preload_length = self.metadata.preload_length as u64;
+        if self.position < preload_length {
+            return Ok(&self.metadata.preload[self.position as usize..preload_length as usize]);
+        }
+
+        if self.buffered().is_empty() {
+            let Some(archive) = &self.archive else {
+                return Ok(&[]);
+            };
+            let archive_position =
+                self.metadata.archive_offset as u64 + self.position - preload_length;
+            let count = READ_BUFFER_SIZE.min((length - self.position) as usize);
+            self.buffer.resize(count, 0);
+            let read = loop {
+                match read_at(archive, &mut self.buffer, archive_position) {
+                    Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+                    result => break result,
+                }
+            };
+            let read = read.inspect_err(|_| self.buffer.clear())?;
+            self.buffer.truncate(read);
+            self.buffer_start = self.position;
+        }
+
+        Ok(self.buffered())
+    }
+
+    fn consume(&mut self, amount: usize) {
+        self.position = (self.position + amount as u64).min(self.length());
     }
 }
 
 impl Seek for VPKFile {
+    /// Moves the cursor. Seeking before the start or past the end of the
+    /// entry fails with [`InvalidInput`](std::io::ErrorKind::InvalidInput)
+    /// and leaves the position unchanged.
     fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
-        let new_position = match pos {
-            SeekFrom::Start(offset) => offset as i64,
-            SeekFrom::End(offset) => self.length() as i64 + offset,
-            SeekFrom::Current(offset) => self.position as i64 + offset,
-        };
-
-        if new_position < 0 {
-            return Err(std::io::Error::new(
-                std::io::ErrorKind::InvalidInput,
-                "Cannot seek to negative position",
-            ));
-        }
+        self.position = seek_position(self.position, self.length(), pos)?;
+        Ok(self.position)
+    }
+}
 
-        self.position = (new_position as u32).min(self.length());
-        Ok(self.position as u64)
+/// Computes the position of a cursor at `position` in an entry of `length`
+/// bytes after seeking to `pos`
+pub(crate) fn seek_position(position: u64, length: u64, pos: SeekFrom) -> std::io::Result<u64> {
+    let new_position = match pos {
+        SeekFrom::Start(offset) => Some(offset),
+        SeekFrom::End(offset) => length.checked_add_signed(offset),
+        SeekFrom::Current(offset) => position.checked_add_signed(offset),
+    };
+
+    match new_position {
+        None => Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "Cannot seek to a negative or overflowing position",
+        )),
+        Some(new_position) if new_position > length => Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("Cannot seek to {new_position}, past the end of the {length} byte file"),
+        )),
+        Some(new_position) => Ok(new_position),
     }
 }
 
@@ -255,11 +461,46 @@ mod tests {
             archive_index: 0,
             archive_offset: 100,
             file_length: 50,
+            raw_path: None,
         };
 
         assert_eq!(metadata.total_length(), 53);
     }
 
+    #[test]
+    fn test_resolve_archive_path_rewrites_file_name_only() {
+        let dir_path = Path::new("build_dir.d").join("pak01_dir.vpk");
+        assert_eq!(
+            VPKFile::resolve_archive_path(&dir_path, 3),
+            Path::new("build_dir.d").join("pak01_003.vpk")
+        );
+        assert_eq!(
+            VPKFile::resolve_archive_path(&dir_path, crate::utils::EMBEDDED_ARCHIVE_INDEX),
+            dir_path
+        );
+    }
+
+    #[test]
+    fn test_vpkfile_reads_archive_after_preload() -> Result<()> {
+        let metadata = FileMetadata {
+            preload: b"ab".to_vec(),
+            crc32: 0,
+            preload_length: 2,
+            archive_index: crate::utils::EMBEDDED_ARCHIVE_INDEX,
+            archive_offset: 10,
+            file_length: 4,
+            raw_path: None,
+        };
+
+        let temp_file = tempfile::NamedTempFile::new()?;
+        std::fs::write(temp_file.path(), b"0123456789cdef")?;
+        let mut vpk_file = VPKFile::new(temp_file.path(), "test".to_string(), metadata)?;
+
+        assert_eq!(vpk_file.read_all()?, b"abcdef");
+
+        Ok(())
+    }
+
     #[test]
     fn test_vpkfile_seek() -> Result<()> {
         let metadata = FileMetadata {
@@ -269,6 +510,7 @@ mod tests {
             archive_index: crate::utils::EMBEDDED_ARCHIVE_INDEX,
             archive_offset: 0,
             file_length: 0,
+            raw_path: None,
         };
 
         let temp_file = tempfile::NamedTempFile::new()?;
@@ -281,6 +523,74 @@ mod tests {
         assert_eq!(vpk_file.seek(SeekFrom::End(-1))?, 4);
         assert_eq!(vpk_file.position(), 4);
 
+        // Out-of-range seeks fail without moving the cursor
+        for pos in [
+            SeekFrom::Start(6),
+            SeekFrom::End(-6),
+            SeekFrom::Current(i64::MIN),
+            SeekFrom::Start(u64::MAX),
+        ] {
+            let error = vpk_file.seek(pos).unwrap_err();
+            assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput);
+            assert_eq!(vpk_file.position(), 4);
+        }
+        assert_eq!(vpk_file.seek(SeekFrom::End(0))?, 5);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_vpkfile_buffered_reads() -> Result<()> {
+        let data: Vec<u8> = (0..20_000u32).map(|i| (i % 251) as u8).collect();
+        let temp_file = tempfile::NamedTempFile::new()?;
+        // Archive data starts 100 bytes into the file, after the preload bytes
+        let mut archive = vec![0xaa; 100];
+        archive.extend_from_slice(&data[4..]);
+        std::fs::write(temp_file.path(), &archive)?;
+
+        let metadata = FileMetadata {
+            preload: data[..4].to_vec(),
+            crc32: 0,
+            preload_length: 4,
+            archive_index: crate::utils::EMBEDDED_ARCHIVE_INDEX,
+            archive_offset: 100,
+            file_length: data.len() as u32 - 4,
+            raw_path: None,
+        };
+        let mut file = VPKFile::new(temp_file.path(), "test".to_string(), metadata)?;
+
+        // BufRead serves the preload bytes, then buffered archive data
+        assert_eq!(file.fill_buf()?, &data[..4]);
+        file.consume(2);
+        let mut start = [0u8; 6];
+        file.read_exact(&mut start)?;
+        assert_eq!(start, data[2..8]);
+        assert_eq!(file.fill_buf()?.len(), READ_BUFFER_SIZE);
+        file.consume(10);
+
+        // Clones move independently of the original
+        let mut clone = file.try_clone()?;
+        assert_eq!(clone.position(), 18);
+        clone.seek(SeekFrom::Start(0))?;
+        let mut all = Vec::new();
+        assert_eq!(clone.read_to_end(&mut all)?, data.len());
+        assert_eq!(all, data);
+        assert_eq!(file.position(), 18);
+
+        let mut rest = Vec::new();
+        file.read_to_end(&mut rest)?;
+        assert_eq!(rest, data[18..]);
+
+        let mut too_long = [0u8; 2];
+        file.seek(SeekFrom::End(-1))?;
+        let error = file.read_exact(&mut too_long).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::UnexpectedEof);
+
+        // Lines split across the buffer boundary come back whole
+        file.seek(SeekFrom::Start(0))?;
+        let lines = file.split(250).count();
+        assert_eq!(lines, data.iter().filter(|&&b| b == 250).count() + 1);
+
         Ok(())
     }
 }
@@ -0,0 +1,196 @@
+use globset::{GlobBuilder, GlobMatcher};
+use regex::Regex;
+
+use crate::entry::Entry;
+use crate::error::{Result, VpkError};
+use crate::vpk::VPK;
+
+/// Prefix that marks a pattern as a regular expression instead of a glob
+pub const REGEX_PREFIX: &str = "regex:";
+
+/// A compiled glob or regular expression matched against entry paths.
+///
+/// Globs follow gitignore conventions: a glob without a `/` matches the file
+/// name in any directory (`*.vmt`), one ending in `/` matches everything
+/// below a directory (`.git/`, `materials/models/`), and any other glob is
+/// anchored at the root (`materials/*.vmt`). `*` does not cross `/`, `**`
+/// does. Regular expressions are matched against the whole path.
+#[derive(Debug, Clone)]
+pub enum Pattern {
+    /// A glob, already rewritten to match whole paths
+    Glob(GlobMatcher),
+    /// A regular expression, unanchored unless it uses `^` and `$`
+    Regex(Regex),
+}
+
+impl Pattern {
+    /// Parses a pattern, treating it as a regular expression if it starts
+    /// with `regex:` and as a glob otherwise
+    pub fn new(pattern: &str) -> Result<Self> {
+        match pattern.strip_prefix(REGEX_PREFIX) {
+            Some(regex) => Self::regex(regex),
+            None => Self::glob(pattern),
+        }
+    }
+
+    /// Compiles a glob
+    pub fn glob(pattern: &str) -> Result<Self> {
+        let normalized = pattern.replace('\\', "/");
+        let glob = match normalized.strip_suffix('/') {
+            Some(dir) if dir.contains('/') => format!("{}/**", dir.trim_start_matches('/')),
+            Some(dir) => format!("**/{dir}/**"),
+            None if normalized.contains('/') => normalized.trim_start_matches('/').to_string(),
+            None => format!("**/{normalized}"),
+        };
+
+        let matcher = GlobBuilder::new(&glob)
+            .literal_separator(true)
+            .build()
+            .map_err(|e| VpkError::InvalidPattern {
+                pattern: pattern.to_string(),
+                reason: e.kind().to_string(),
+            })?
+            .compile_matcher();
+        Ok(Pattern::Glob(matcher))
+    }
+
+    /// Compiles a regular expression
+    pub fn regex(pattern: &str) -> Result<Self> {
+        let regex = Regex::new(pattern).map_err(|e| VpkError::InvalidPattern {
+            pattern: pattern.to_string(),
+            reason: e.to_string(),
+        })?;
+        Ok(Pattern::Regex(regex))
+    }
+
+    /// Returns true if the pattern matches a `/`-separated entry path
+    pub fn is_match(&self, path: &str) -> bool {
+        match self {
+            Pattern::Glob(glob) => glob.is_match(path),
+            Pattern::Regex(regex) => regex.is_match(path),
+        }
+    }
+}
+
+/// Include and exclude patterns selecting a subset of paths.
+///
+/// A path matches if it matches any include pattern, or there are none, and
+/// no exclude pattern.
+#[derive(Debug, Clone, Default)]
+pub struct PathFilter {
+    include: Vec<Pattern>,
+    exclude: Vec<Pattern>,
+}
+
+impl PathFilter {
+    /// Creates a filter that matches every path
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only keeps paths matching `pattern` or another include pattern
+    pub fn include(mut self, pattern: &str) -> Result<Self> {
+        self.include.push(Pattern::new(pattern)?);
+        Ok(self)
+    }
+
+    /// Drops paths matching `pattern`
+    pub fn exclude(mut self, pattern: &str) -> Result<Self> {
+        self.exclude.push(Pattern::new(pattern)?);
+        Ok(self)
+    }
+
+    /// Returns true if the filter has no patterns and matches everything
+    pub fn is_empty(&self) -> bool {
+        self.include.is_empty() && self.exclude.is_empty()
+    }
+
+    /// Returns true if `path` is selected by the filter
+    pub fn is_match(&self, path: &str) -> bool {
+        (self.include.is_empty() || self.include.iter().any(|p| p.is_match(path)))
+            && !self.exclude.iter().any(|p| p.is_match(path))
+    }
+}
+
+impl VPK {
+    /// Gets the entries matching a glob, or a regular expression prefixed
+    /// with `regex:`, sorted by path. See [`Pattern`] for the syntax.
+    pub fn matching(&self, pattern: &str) -> Result<Vec<Entry<'_>>> {
+        let pattern = Pattern::new(pattern)?;
+        self.index()?;
+        let mut entries: Vec<_> = self
+            .entries()
+            .filter(|entry| pattern.is_match(entry.path()))
+            .collect();
+        entries.sort_by_key(|entry| entry.path());
+        Ok(entries)
+    }
+
+    /// Gets the entries selected by a filter, sorted by path
+    pub fn filtered(&self, filter: &PathFilter) -> Vec<Entry<'_>> {
+        let mut entries: Vec<_> = self
+            .entries()
+            .filter(|entry| filter.is_match(entry.path()))
+            .collect();
+        entries.sort_by_key(|entry| entry.path());
+        entries
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_patterns() -> Result<()> {
+        let vmt = Pattern::new("*.vmt")?;
+        assert!(vmt.is_match("foo.vmt"));
+        assert!(vmt.is_match("materials/models/foo.vmt"));
+        assert!(!vmt.is_match("materials/foo.vtf"));
+
+        let anchored = Pattern::new("materials/models/*.vmt")?;
+        assert!(anchored.is_match("materials/models/foo.vmt"));
+        assert!(!anchored.is_match("materials/models/props/foo.vmt"));
+        assert!(!anchored.is_match("old/materials/models/foo.vmt"));
+
+        let recursive = Pattern::new("materials/models/**/*.vmt")?;
+        assert!(recursive.is_match("materials/models/foo.vmt"));
+        assert!(recursive.is_match("materials/models/props/foo.vmt"));
+
+        let git = Pattern::new(".git/")?;
+        assert!(git.is_match(".git/config"));
+        assert!(git.is_match("sub/.git/objects/ab"));
+        assert!(!git.is_match(".gitignore"));
+
+        let dir = Pattern::new("materials/models/")?;
+        assert!(dir.is_match("materials/models/props/foo.vmt"));
+        assert!(!dir.is_match("materials/foo.vmt"));
+
+        let regex = Pattern::new(r"regex:^sound/.*\.(wav|mp3)$")?;
+        assert!(regex.is_match("sound/ui/click.wav"));
+        assert!(!regex.is_match("sound/ui/click.ogg"));
+
+        assert!(matches!(
+            Pattern::new("regex:("),
+            Err(VpkError::InvalidPattern { .. })
+        ));
+        assert!(matches!(
+            Pattern::new("[a"),
+            Err(VpkError::InvalidPattern { .. })
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_path_filter() -> Result<()> {
+        assert!(PathFilter::new().is_match("anything.txt"));
+
+        let filter = PathFilter::new().include("materials/")?.exclude("*.psd")?;
+        assert!(filter.is_match("materials/foo.vmt"));
+        assert!(!filter.is_match("materials/foo.psd"));
+        assert!(!filter.is_match("scripts/foo.lua"));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,125 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use serde::Serialize;
+
+use crate::error::{Result, VpkError};
+use crate::file::VPKFile;
+use crate::utils::EMBEDDED_ARCHIVE_INDEX;
+use crate::vpk::{VPK, VPKChecksums, VPKHeader, VPKSections, VPKVersion};
+
+/// Raw layout of an opened VPK: its header, sections, stored checksums and
+/// the archive chunk files its entries refer to
+#[derive(Debug, Clone, Serialize)]
+pub struct VPKInfo {
+    /// Path of the directory file
+    pub path: PathBuf,
+    /// Size of the directory file on disk
+    pub file_size: u64,
+    pub header: VPKHeader,
+    pub sections: VPKSections,
+    pub checksums: Option<VPKChecksums>,
+    /// Number of entries in the tree
+    pub entries: usize,
+    /// Entries whose data is stored in the directory file, or only in the tree
+    pub embedded_entries: usize,
+    /// Archive chunk files holding entry data, sorted by index
+    pub chunks: Vec<ChunkInfo>,
+    /// Bytes of the directory file after the last section, or after the
+    /// embedded data for V1 files
+    pub trailing_bytes: u64,
+}
+
+/// An archive chunk file (`_NNN.vpk`) and the entries stored in it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ChunkInfo {
+    pub index: u16,
+    pub path: PathBuf,
+    /// Size of the chunk file, or `None` if it does not exist
+    pub file_size: Option<u64>,
+    /// Number of entries with data in the chunk
+    pub entries: usize,
+    /// Bytes of entry data stored in the chunk
+    pub data_length: u64,
+}
+
+impl VPK {
+    /// Describes the header, section layout and archive set of the VPK.
+    ///
+    /// Chunk sizes come from the file system; chunk files are not opened.
+    pub fn info(&self) -> Result<VPKInfo> {
+        let path = self.path().ok_or(VpkError::NotSaved("inspect"))?;
+        let file_size = fs::metadata(path)
+            .map_err(|e| VpkError::io(format!("Failed to stat VPK file: {}", path.display()), e))?
+            .len();
+
+        self.index()?;
+        let mut embedded_entries = 0;
+        let mut embedded_data_end = 0u64;
+        let mut chunks: BTreeMap<u16, (usize, u64)> = BTreeMap::new();
+        for entry in self.entries() {
+            let metadata = entry.metadata();
+            // Entries without archive data never read their chunk
+            if metadata.archive_index == EMBEDDED_ARCHIVE_INDEX || metadata.file_length == 0 {
+                embedded_entries += 1;
+                // Chunk offsets are relative to their chunk, not the directory file
+                if metadata.archive_index == EMBEDDED_ARCHIVE_INDEX {
+                    embedded_data_end = embedded_data_end
+                        .max(metadata.archive_offset as u64 + metadata.file_length as u64);
+                }
+            } else {
+                let (entries, data_length) = chunks.entry(metadata.archive_index).or_default();
+                *entries += 1;
+                *data_length += metadata.file_length as u64;
+            }
+        }
+
+        let chunks = chunks
+            .into_iter()
+            .map(|(index, (entries, data_length))| {
+                let chunk_path = VPKFile::resolve_archive_path(path, index);
+                let file_size = match fs::metadata(&chunk_path) {
+                    Ok(metadata) => Some(metadata.len()),
+                    Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+                    Err(e) => {
+                        return Err(VpkError::io(
+                            format!("Failed to stat VPK archive: {}", chunk_path.display()),
+                            e,
+                        ));
+                    }
+                };
+                Ok(ChunkInfo {
+                    index,
+                    path: chunk_path,
+                    file_size,
+                    entries,
+                    data_length,
+                })
+            })
+            .collect::<Result<_>>()?;
+
+        // V1 headers do not record the embedded data length, so it ends
+        // with the last entry stored there. Embedded entry offsets are
+        // absolute once parsed.
+        let header = self.header().clone();
+        let sections = header.sections();
+        let data_end = match header.version {
+            VPKVersion::V2 => sections.signature.end(),
+            VPKVersion::V1 => sections.tree.end().max(embedded_data_end),
+        };
+
+        Ok(VPKInfo {
+            path: path.to_path_buf(),
+            file_size,
+            trailing_bytes: file_size.saturating_sub(data_end),
+            header,
+            sections,
+            checksums: self.checksums().cloned(),
+            entries: self.file_count(),
+            embedded_entries,
+            chunks,
+        })
+    }
+}
@@ -3,14 +3,41 @@
 //! This library provides functionality to read, write, and manipulate VPK files
 //! used by Valve's Source engine games.
 
+mod archive;
+#[cfg(feature = "tokio")]
+pub mod async_io;
+pub mod browse;
+pub mod entry;
+pub mod error;
+pub mod extract;
 pub mod file;
+pub mod filter;
+pub mod info;
+pub mod manifest;
+pub mod pack;
+mod parallel;
+pub mod progress;
+pub mod usage;
 pub mod utils;
+pub mod verify;
 pub mod vpk;
 
+#[cfg(feature = "tokio")]
+pub use async_io::AsyncVPKFile;
+pub use browse::DirEntry;
+pub use entry::{Entries, Entry, EntryLocation};
+pub use error::{Result, VpkError};
+pub use extract::{ExtractOptions, UnsafePathPolicy};
 pub use file::VPKFile;
-pub use vpk::VPK;
-
-use anyhow::Result;
+pub use filter::{PathFilter, Pattern};
+pub use info::{ChunkInfo, VPKInfo};
+pub use manifest::{Manifest, ManifestEntry};
+pub use pack::{ConflictPolicy, PackOptions};
+pub use progress::{Operation, Progress, ProgressEvent};
+pub use usage::{DiskUsage, UsageTotal};
+pub use utils::NameEncoding;
+pub use verify::{VerifyOptions, VerifyReport};
+pub use vpk::{OpenOptions, PathLookup, VPK};
 
 /// Opens an existing VPK file for reading
 pub fn open<P: AsRef<std::path::Path>>(path: P) -> Result<VPK> {
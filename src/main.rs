@@ -1,31 +1,189 @@
 use anyhow::{Context, Result};
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::io::{self, Write};
-use std::path::PathBuf;
+use std::io::{self, IsTerminal, Write};
+use std::ops::ControlFlow;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
-use valve_pak::VPK;
+use valve_pak::utils::{EMBEDDED_ARCHIVE_INDEX, to_hex};
+use valve_pak::{
+    ConflictPolicy, DirEntry, DiskUsage, Entry, ExtractOptions, Manifest, PackOptions, PathFilter,
+    Progress, ProgressEvent, UnsafePathPolicy, UsageTotal, VPK, VerifyOptions, VerifyReport,
+};
 
 #[derive(Parser)]
 #[command(name = "vpk")]
 #[command(about = "A CLI tool for working with Valve Pak (VPK) files")]
 #[command(version = "1.4.0")]
 struct Cli {
+    /// Do not draw progress bars
+    #[arg(long, global = true)]
+    no_progress: bool,
     #[command(subcommand)]
     command: Commands,
 }
 
+/// What to do with entry paths that would write outside the output directory
+#[derive(Clone, Copy, ValueEnum)]
+enum UnsafePaths {
+    /// Abort extraction
+    Reject,
+    /// Strip roots and `..` components
+    Sanitize,
+}
+
+/// Options shared by commands that write entries to disk
+#[derive(Args)]
+struct ExtractArgs {
+    /// How to handle absolute paths, `..` components and NUL bytes in entry paths
+    #[arg(long, value_enum, default_value_t = UnsafePaths::Reject)]
+    unsafe_paths: UnsafePaths,
+    /// Also treat Windows reserved names such as `CON` or `a?.txt` as unsafe
+    /// (always on when running on Windows)
+    #[arg(long)]
+    windows_names: bool,
+}
+
+impl ExtractArgs {
+    fn options(&self) -> ExtractOptions {
+        let policy = match self.unsafe_paths {
+            UnsafePaths::Reject => UnsafePathPolicy::Reject,
+            UnsafePaths::Sanitize => UnsafePathPolicy::Sanitize,
+        };
+        ExtractOptions::new()
+            .unsafe_paths(policy)
+            .windows_names(self.windows_names || cfg!(windows))
+    }
+}
+
+/// Patterns selecting which entries a command works on
+#[derive(Args)]
+struct FilterArgs {
+    /// Only include paths matching this glob, or a regex prefixed with `regex:`
+    /// (repeatable)
+    #[arg(long, value_name = "PATTERN")]
+    include: Vec<String>,
+    /// Skip paths matching this glob, or a regex prefixed with `regex:`
+    /// (repeatable)
+    #[arg(long, value_name = "PATTERN")]
+    exclude: Vec<String>,
+}
+
+impl FilterArgs {
+    fn filter(&self) -> Result<PathFilter> {
+        let mut filter = PathFilter::new();
+        for pattern in &self.include {
+            filter = filter.include(pattern)?;
+        }
+        for pattern in &self.exclude {
+            filter = filter.exclude(pattern)?;
+        }
+        Ok(filter)
+    }
+}
+
+/// How `list`, `verify` and `info` print their results
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Format {
+    /// Human-readable tables
+    Text,
+    /// Pretty-printed JSON
+    Json,
+    /// Comma-separated values with a header row
+    Csv,
+    /// Tab-separated values with a header row
+    Tsv,
+}
+
+/// Output format options shared by commands that print reports
+#[derive(Args)]
+struct FormatArgs {
+    /// Output format
+    #[arg(long, value_enum, default_value_t = Format::Text)]
+    format: Format,
+    /// Same as `--format json`
+    #[arg(long, conflicts_with = "format")]
+    json: bool,
+}
+
+impl FormatArgs {
+    fn format(&self) -> Format {
+        if self.json { Format::Json } else { self.format }
+    }
+}
+
+/// A source directory and the archive prefix it is packed below
+#[derive(Clone)]
+struct Mount {
+    source: PathBuf,
+    prefix: String,
+}
+
+/// Parses `DIR` or `DIR=PREFIX`; paths that exist are never split
+fn parse_mount(value: &str) -> Result<Mount, String> {
+    let (source, prefix) = match value.rsplit_once('=') {
+        Some((source, prefix)) if !Path::new(value).exists() => (source, prefix),
+        _ => (value, ""),
+    };
+    if source.is_empty() {
+        return Err("expected DIR or DIR=PREFIX".to_string());
+    }
+    Ok(Mount {
+        source: PathBuf::from(source),
+        prefix: prefix.to_string(),
+    })
+}
+
+/// What to do when several source files map to the same archive path
+#[derive(Clone, Copy, ValueEnum)]
+enum OnConflict {
+    /// Abort packing
+    Error,
+    /// Keep the file from the first directory
+    First,
+    /// Keep the file from the last directory
+    Last,
+}
+
+impl OnConflict {
+    fn policy(self) -> ConflictPolicy {
+        match self {
+            OnConflict::Error => ConflictPolicy::Error,
+            OnConflict::First => ConflictPolicy::FirstWins,
+            OnConflict::Last => ConflictPolicy::LastWins,
+        }
+    }
+}
+
 #[derive(Subcommand)]
 enum Commands {
-    /// Pack a directory into a VPK file
+    /// Pack a directory, or the files listed in a manifest, into a VPK file
     Pack {
-        /// Directory to pack
-        directory: PathBuf,
+        /// Directory to pack as `DIR` or `DIR=PREFIX`, or a text or JSON
+        /// (`.json`) manifest file
+        #[arg(value_parser = parse_mount)]
+        directory: Mount,
         /// Output VPK file path
         output: PathBuf,
+        /// Also pack a directory below a prefix in the archive, as `DIR=PREFIX`
+        /// (repeatable)
+        #[arg(long, value_name = "DIR=PREFIX", value_parser = parse_mount)]
+        mount: Vec<Mount>,
+        /// What to do when several files map to the same archive path
+        #[arg(long, value_enum, default_value_t = OnConflict::Error)]
+        on_conflict: OnConflict,
         /// Verbose output
         #[arg(short, long)]
         verbose: bool,
+        /// Pack files excluded by `.vpkignore` files, and the `.vpkignore` files themselves
+        #[arg(long)]
+        no_ignore: bool,
+        #[command(flatten)]
+        filter: FilterArgs,
     },
     /// Unpack a VPK file to a directory
     Unpack {
@@ -36,6 +194,13 @@ enum Commands {
         /// Verbose output
         #[arg(short, long)]
         verbose: bool,
+        /// Number of extraction threads (defaults to the number of CPUs)
+        #[arg(short, long, default_value_t = 0, hide_default_value = true)]
+        jobs: usize,
+        #[command(flatten)]
+        extract: ExtractArgs,
+        #[command(flatten)]
+        filter: FilterArgs,
     },
     /// List files in a VPK
     List {
@@ -44,11 +209,22 @@ enum Commands {
         /// Show detailed information
         #[arg(short, long)]
         detailed: bool,
+        #[command(flatten)]
+        format: FormatArgs,
+        #[command(flatten)]
+        filter: FilterArgs,
     },
-    /// Verify VPK checksums (V2 only)
+    /// Verify VPK checksums (V2 only) and file CRCs
     Verify {
         /// VPK file to verify
         input: PathBuf,
+        /// Number of verification threads (defaults to the number of CPUs)
+        #[arg(short, long, default_value_t = 0, hide_default_value = true)]
+        jobs: usize,
+        #[command(flatten)]
+        format: FormatArgs,
+        #[command(flatten)]
+        filter: FilterArgs,
     },
     /// Extract a single file from VPK
     Extract {
@@ -56,58 +232,291 @@ enum Commands {
         input: PathBuf,
         /// File path within the VPK
         file_path: String,
-        /// Output file path
+        /// Output file path, a directory to extract the entry below, or `-`
+        /// to write it to stdout
         output: PathBuf,
+        #[command(flatten)]
+        extract: ExtractArgs,
+    },
+    /// Show the header, section layout, checksums and archive chunks of a VPK
+    Info {
+        /// VPK file to inspect
+        input: PathBuf,
+        #[command(flatten)]
+        format: FormatArgs,
+    },
+    /// Show the directory hierarchy of a VPK with sizes
+    Tree {
+        /// VPK file to show
+        input: PathBuf,
+        /// Directory to start from (defaults to the root)
+        #[arg(default_value = "")]
+        prefix: String,
+        /// Deepest directory level to descend into
+        #[arg(short = 'L', long)]
+        depth: Option<usize>,
+    },
+    /// Sum file sizes per directory, extension and archive chunk
+    Du {
+        /// VPK file to summarize
+        input: PathBuf,
+        /// Only count files below this directory (defaults to the root)
+        #[arg(default_value = "")]
+        prefix: String,
+        /// Deepest directory level below the prefix to list
+        #[arg(short, long, default_value_t = 1)]
+        depth: usize,
+        /// Number of largest files to list
+        #[arg(short = 'n', long, default_value_t = 10)]
+        top: usize,
+    },
+    /// Write the contents of files in a VPK to stdout
+    Cat {
+        /// VPK file to read from
+        input: PathBuf,
+        /// File paths within the VPK, written in order
+        #[arg(required = true)]
+        paths: Vec<String>,
     },
 }
 
+/// Progress bar drawn on stderr while files are saved, extracted or verified
+struct ProgressBar {
+    state: Mutex<ProgressBarState>,
+}
+
+#[derive(Default)]
+struct ProgressBarState {
+    last_draw: Option<Instant>,
+    finished: bool,
+}
+
+impl ProgressBar {
+    const WIDTH: usize = 30;
+    const PATH_WIDTH: usize = 40;
+    const REDRAW_INTERVAL: Duration = Duration::from_millis(100);
+
+    /// Creates a progress bar if enabled and stderr is a terminal
+    fn new(enabled: bool) -> Option<Arc<Self>> {
+        (enabled && io::stderr().is_terminal()).then(|| {
+            Arc::new(ProgressBar {
+                state: Mutex::new(ProgressBarState::default()),
+            })
+        })
+    }
+}
+
+impl Progress for ProgressBar {
+    fn update(&self, event: &ProgressEvent<'_>) -> ControlFlow<()> {
+        let mut state = self.state.lock().expect("progress bar lock poisoned");
+        let last = event.files_done == event.files_total;
+        let recently_drawn = state
+            .last_draw
+            .is_some_and(|time| time.elapsed() < Self::REDRAW_INTERVAL);
+        if state.finished || (recently_drawn && !last) {
+            return ControlFlow::Continue(());
+        }
+
+        if last {
+            // Clear the bar so the command's summary starts on a clean line
+            eprint!("\r\x1b[2K");
+            state.finished = true;
+            return ControlFlow::Continue(());
+        }
+
+        let fraction = if event.bytes_total > 0 {
+            event.bytes_done as f64 / event.bytes_total as f64
+        } else {
+            event.files_done as f64 / event.files_total.max(1) as f64
+        };
+        let filled = ((fraction * Self::WIDTH as f64) as usize).min(Self::WIDTH);
+        let skip = event.path.chars().count().saturating_sub(Self::PATH_WIDTH);
+        let path: String = event.path.chars().skip(skip).collect();
+        eprint!(
+            "\r\x1b[2K[{}{}] {:>3}% {}/{} files {}{path}",
+            "#".repeat(filled),
+            " ".repeat(Self::WIDTH - filled),
+            (fraction * 100.0) as u32,
+            event.files_done,
+            event.files_total,
+            if skip > 0 { "..." } else { "" },
+        );
+        let _ = io::stderr().flush();
+        state.last_draw = Some(Instant::now());
+        ControlFlow::Continue(())
+    }
+}
+
+impl Drop for ProgressBar {
+    fn drop(&mut self) {
+        let state = self.state.get_mut().expect("progress bar lock poisoned");
+        if state.last_draw.is_some() && !state.finished {
+            eprintln!();
+        }
+    }
+}
+
 fn main() -> Result<()> {
     let cli = Cli::parse();
+    let progress = ProgressBar::new(!cli.no_progress);
 
     match cli.command {
         Commands::Pack {
             directory,
             output,
+            mount,
+            on_conflict,
+            verbose,
+            no_ignore,
+            filter,
+        } => pack_command(
+            &[vec![directory], mount].concat(),
+            output,
             verbose,
-        } => pack_command(directory, output, verbose),
+            &PackOptions::new()
+                .use_ignore_files(!no_ignore)
+                .conflict_policy(on_conflict.policy())
+                .filter(filter.filter()?),
+            progress.as_deref().map(|bar| bar as &dyn Progress),
+        ),
         Commands::Unpack {
             input,
             output,
             verbose,
-        } => unpack_command(input, output, verbose),
-        Commands::List { input, detailed } => list_command(input, detailed),
-        Commands::Verify { input } => verify_command(input),
+            jobs,
+            extract,
+            filter,
+        } => {
+            let mut options = extract.options().jobs(jobs);
+            if let Some(bar) = progress {
+                options = options.progress(bar);
+            }
+            unpack_command(input, output, verbose, &options, &filter.filter()?)
+        }
+        Commands::List {
+            input,
+            detailed,
+            format,
+            filter,
+        } => list_command(input, detailed, format.format(), &filter.filter()?),
+        Commands::Verify {
+            input,
+            jobs,
+            format,
+            filter,
+        } => {
+            let format = format.format();
+            let mut options = VerifyOptions::new().jobs(jobs);
+            if let Some(bar) = progress.filter(|_| format == Format::Text) {
+                options = options.progress(bar);
+            }
+            verify_command(input, &options, format, &filter.filter()?)
+        }
         Commands::Extract {
             input,
             file_path,
             output,
-        } => extract_command(input, file_path, output),
+            extract,
+        } => ignore_broken_pipe(extract_command(
+            input,
+            file_path,
+            output,
+            &extract.options(),
+        )),
+        Commands::Info { input, format } => info_command(input, format.format()),
+        Commands::Tree {
+            input,
+            prefix,
+            depth,
+        } => tree_command(input, &prefix, depth),
+        Commands::Du {
+            input,
+            prefix,
+            depth,
+            top,
+        } => du_command(input, &prefix, depth, top),
+        Commands::Cat { input, paths } => {
+            ignore_broken_pipe(cat_command(input, &paths, &mut io::stdout().lock()))
+        }
     }
 }
 
-fn pack_command(directory: PathBuf, output: PathBuf, verbose: bool) -> Result<()> {
-    if !directory.is_dir() {
-        anyhow::bail!("Input path is not a directory: {}", directory.display());
+/// Treats a closed stdout, e.g. when piped into `head`, as success
+fn ignore_broken_pipe(result: Result<()>) -> Result<()> {
+    match result {
+        Err(e)
+            if e.chain().any(|cause| {
+                cause
+                    .downcast_ref::<io::Error>()
+                    .is_some_and(|e| e.kind() == io::ErrorKind::BrokenPipe)
+            }) =>
+        {
+            Ok(())
+        }
+        result => result,
     }
+}
 
-    if verbose {
-        println!("Packing directory: {}", directory.display());
-    }
+fn pack_command(
+    mounts: &[Mount],
+    output: PathBuf,
+    verbose: bool,
+    options: &PackOptions,
+    progress: Option<&dyn Progress>,
+) -> Result<()> {
+    let vpk = match mounts {
+        [mount] if mount.source.is_file() && mount.prefix.is_empty() => {
+            let manifest_path = &mount.source;
+            if verbose {
+                println!("Packing manifest: {}", manifest_path.display());
+            }
 
-    let vpk = VPK::from_directory(&directory).with_context(|| {
-        format!(
-            "Failed to create VPK from directory: {}",
-            directory.display()
-        )
-    })?;
+            let manifest = Manifest::from_path(manifest_path)
+                .with_context(|| format!("Failed to read manifest: {}", manifest_path.display()))?;
+            options.pack_manifest(&manifest).with_context(|| {
+                format!(
+                    "Failed to create VPK from manifest: {}",
+                    manifest_path.display()
+                )
+            })?
+        }
+        _ => {
+            for mount in mounts {
+                if !mount.source.is_dir() {
+                    anyhow::bail!("Input path is not a directory: {}", mount.source.display());
+                }
+                if verbose {
+                    match mount.prefix.as_str() {
+                        "" => println!("Packing directory: {}", mount.source.display()),
+                        prefix => {
+                            println!("Packing directory: {} at {prefix}/", mount.source.display())
+                        }
+                    }
+                }
+            }
+
+            options
+                .pack_roots(mounts.iter().map(|m| (&m.source, &m.prefix)))
+                .with_context(|| match mounts {
+                    [mount] => format!(
+                        "Failed to create VPK from directory: {}",
+                        mount.source.display()
+                    ),
+                    _ => "Failed to create VPK from directories".to_string(),
+                })?
+        }
+    };
 
     if verbose {
         println!("Found {} files", vpk.file_count());
         println!("Writing VPK to: {}", output.display());
     }
 
-    vpk.save(&output)
-        .with_context(|| format!("Failed to save VPK to: {}", output.display()))?;
+    match progress {
+        Some(progress) => vpk.save_with_progress(&output, progress),
+        None => vpk.save(&output),
+    }
+    .with_context(|| format!("Failed to save VPK to: {}", output.display()))?;
 
     println!(
         "Successfully packed {} files into {}",
@@ -117,7 +526,13 @@ fn pack_command(directory: PathBuf, output: PathBuf, verbose: bool) -> Result<()
     Ok(())
 }
 
-fn unpack_command(input: PathBuf, output: PathBuf, verbose: bool) -> Result<()> {
+fn unpack_command(
+    input: PathBuf,
+    output: PathBuf,
+    verbose: bool,
+    options: &ExtractOptions,
+    filter: &PathFilter,
+) -> Result<()> {
     if !input.is_file() {
         anyhow::bail!("Input path is not a file: {}", input.display());
     }
@@ -139,80 +554,90 @@ fn unpack_command(input: PathBuf, output: PathBuf, verbose: bool) -> Result<()>
     fs::create_dir_all(&output)
         .with_context(|| format!("Failed to create output directory: {}", output.display()))?;
 
-    let mut extracted_count = 0;
-    for file_path in vpk.file_paths() {
-        if verbose {
-            println!("Extracting: {file_path}");
-        }
-
-        let mut vpk_file = vpk
-            .get_file(file_path)
-            .with_context(|| format!("Failed to get file: {file_path}"))?;
-
-        let output_file_path = output.join(file_path);
-
-        // Create parent directories if needed
-        if let Some(parent) = output_file_path.parent() {
-            fs::create_dir_all(parent).with_context(|| {
-                format!("Failed to create parent directory: {}", parent.display())
-            })?;
+    let entries = vpk.filtered(filter);
+    if verbose {
+        for entry in &entries {
+            println!("Extracting: {}", entry.path());
         }
-
-        vpk_file
-            .save(&output_file_path)
-            .with_context(|| format!("Failed to extract file: {file_path}"))?;
-
-        extracted_count += 1;
     }
 
+    let extracted_count = vpk
+        .extract_entries(&entries, &output, options)
+        .with_context(|| format!("Failed to extract files to: {}", output.display()))?;
+
     println!(
         "Successfully extracted {} files to {}",
         extracted_count,
         output.display()
     );
+    let skipped = entries.len() - extracted_count;
+    if skipped > 0 {
+        println!("Skipped {skipped} entries that extract to the same file as another entry");
+    }
     Ok(())
 }
 
-fn list_command(input: PathBuf, detailed: bool) -> Result<()> {
+fn list_command(input: PathBuf, detailed: bool, format: Format, filter: &PathFilter) -> Result<()> {
     if !input.is_file() {
         anyhow::bail!("Input path is not a file: {}", input.display());
     }
 
     let vpk =
         VPK::open(&input).with_context(|| format!("Failed to open VPK: {}", input.display()))?;
+    let entries = vpk.filtered(filter);
+
+    if format != Format::Text {
+        let rows: Vec<_> = entries.iter().map(EntryRow::new).collect();
+        return match format {
+            Format::Json => print_json(&rows),
+            _ => print_table(format, EntryRow::COLUMNS, rows.iter().map(EntryRow::fields)),
+        };
+    }
 
     println!("VPK: {}", input.display());
     println!("Version: {:?}", vpk.version());
-    println!("Files: {}", vpk.file_count());
+    let total = vpk.file_count();
+    if entries.len() == total {
+        println!("Files: {total}");
+    } else {
+        println!("Files: {} of {total}", entries.len());
+    }
     println!();
 
+    // Size the path column to fit the longest path
+    let width = entries
+        .iter()
+        .map(|entry| entry.path().chars().count())
+        .max()
+        .unwrap_or(0)
+        .max("Path".len());
     if detailed {
-        println!("{:<50} {:>10} {:>10}", "Path", "Size", "CRC32");
-        println!("{}", "-".repeat(75));
+        println!("{:<width$} {:>10} {:>10}", "Path", "Size", "CRC32");
+        println!("{}", "-".repeat(width + 22));
     }
 
-    let mut files: Vec<_> = vpk.file_paths().collect();
-    files.sort();
-
-    for file_path in files {
+    for entry in &entries {
         if detailed {
-            if let Ok(vpk_file) = vpk.get_file(file_path) {
-                println!(
-                    "{:<50} {:>10} {:>10x}",
-                    file_path,
-                    vpk_file.length(),
-                    vpk_file.metadata().crc32
-                );
-            }
+            println!(
+                "{:<width$} {:>10} {:>10x}",
+                entry.path(),
+                entry.size(),
+                entry.crc32()
+            );
         } else {
-            println!("{file_path}");
+            println!("{}", entry.path());
         }
     }
 
     Ok(())
 }
 
-fn verify_command(input: PathBuf) -> Result<()> {
+fn verify_command(
+    input: PathBuf,
+    options: &VerifyOptions,
+    format: Format,
+    filter: &PathFilter,
+) -> Result<()> {
     if !input.is_file() {
         anyhow::bail!("Input path is not a file: {}", input.display());
     }
@@ -220,68 +645,89 @@ fn verify_command(input: PathBuf) -> Result<()> {
     let vpk =
         VPK::open(&input).with_context(|| format!("Failed to open VPK: {}", input.display()))?;
 
-    match vpk.version() {
-        valve_pak::vpk::VPKVersion::V1 => {
-            println!("VPK V1 files do not support checksum verification");
-            return Ok(());
+    if format != Format::Text {
+        let entries = vpk.filtered(filter);
+        let report = vpk.verify_entries(&entries, options)?;
+        let rows = VerifyRow::rows(&entries, &report);
+        match format {
+            Format::Json => print_json(&VerifyOutput {
+                report: &report,
+                entries: rows,
+            })?,
+            _ => print_table(
+                format,
+                VerifyRow::COLUMNS,
+                rows.iter().map(VerifyRow::fields),
+            )?,
         }
-        valve_pak::vpk::VPKVersion::V2 => {
-            print!("Verifying VPK checksums... ");
-            io::stdout().flush()?;
-
-            match vpk.verify() {
-                Ok(true) => println!("✓ VPK checksums are valid"),
-                Ok(false) => {
-                    println!("✗ VPK checksums are invalid");
-                    std::process::exit(1);
-                }
-                Err(e) => {
-                    println!("✗ Failed to verify: {e}");
-                    std::process::exit(1);
-                }
+        if !report.is_ok() {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    if vpk.version() == valve_pak::vpk::VPKVersion::V2 {
+        print!("Verifying VPK checksums... ");
+        io::stdout().flush()?;
+
+        match vpk.verify() {
+            Ok(true) => println!("✓ VPK checksums are valid"),
+            Ok(false) => {
+                println!("✗ VPK checksums are invalid");
+                std::process::exit(1);
+            }
+            Err(e) => {
+                println!("✗ Failed to verify: {e}");
+                std::process::exit(1);
             }
         }
     }
 
     // Also verify individual files
     println!("Verifying individual files...");
-    let mut verified = 0;
-    let mut failed = 0;
-
-    for file_path in vpk.file_paths() {
-        if let Ok(mut vpk_file) = vpk.get_file(file_path) {
-            match vpk_file.verify() {
-                Ok(true) => {
-                    verified += 1;
-                    print!(".");
-                }
-                Ok(false) => {
-                    failed += 1;
-                    println!("\n✗ CRC mismatch: {file_path}");
-                }
-                Err(_) => {
-                    failed += 1;
-                    println!("\n✗ Failed to verify: {file_path}");
-                }
-            }
-        }
+    let report = vpk.verify_entries(&vpk.filtered(filter), options)?;
 
-        if (verified + failed) % 50 == 0 {
-            println!();
-        }
+    for chunk in &report.missing_chunks {
+        println!(
+            "✗ Missing archive {:03}: {} ({} files)",
+            chunk.index,
+            chunk.path.display(),
+            chunk.entries
+        );
+    }
+    for mismatch in &report.mismatches {
+        println!(
+            "✗ CRC mismatch: {} (expected {:08x}, got {:08x})",
+            mismatch.path, mismatch.expected, mismatch.actual
+        );
+    }
+    for unreadable in &report.unreadable {
+        println!(
+            "✗ Failed to verify: {} ({})",
+            unreadable.path, unreadable.error
+        );
     }
 
-    println!();
-    println!("Verification complete: {verified} verified, {failed} failed");
+    println!(
+        "Verification complete: {} verified, {} failed in {:.2?}",
+        report.passed,
+        report.failed(),
+        report.elapsed
+    );
 
-    if failed > 0 {
+    if !report.is_ok() {
         std::process::exit(1);
     }
 
     Ok(())
 }
 
-fn extract_command(input: PathBuf, file_path: String, output: PathBuf) -> Result<()> {
+fn extract_command(
+    input: PathBuf,
+    file_path: String,
+    output: PathBuf,
+    options: &ExtractOptions,
+) -> Result<()> {
     if !input.is_file() {
         anyhow::bail!("Input path is not a file: {}", input.display());
     }
@@ -293,19 +739,32 @@ fn extract_command(input: PathBuf, file_path: String, output: PathBuf) -> Result
         anyhow::bail!("File not found in VPK: {}", file_path);
     }
 
-    let mut vpk_file = vpk
-        .get_file(&file_path)
-        .with_context(|| format!("Failed to get file: {file_path}"))?;
-
-    // Create parent directories if needed
-    if let Some(parent) = output.parent() {
-        fs::create_dir_all(parent)
-            .with_context(|| format!("Failed to create parent directory: {}", parent.display()))?;
+    // Nothing else is printed, so the output can be piped
+    if output == Path::new("-") {
+        return write_entries(&vpk, &[file_path], &mut io::stdout().lock());
     }
 
-    vpk_file
-        .save(&output)
-        .with_context(|| format!("Failed to extract file to: {}", output.display()))?;
+    // Extracting into a directory keeps the entry path, so it must be checked
+    let output = if output.is_dir() {
+        vpk.extract_file(&file_path, &output, options)
+            .with_context(|| format!("Failed to extract file below: {}", output.display()))?
+    } else {
+        let mut vpk_file = vpk
+            .get_file(&file_path)
+            .with_context(|| format!("Failed to get file: {file_path}"))?;
+
+        // Create parent directories if needed
+        if let Some(parent) = output.parent() {
+            fs::create_dir_all(parent).with_context(|| {
+                format!("Failed to create parent directory: {}", parent.display())
+            })?;
+        }
+
+        vpk_file
+            .save(&output)
+            .with_context(|| format!("Failed to extract file to: {}", output.display()))?;
+        output
+    };
 
     println!(
         "Successfully extracted '{}' to {}",
@@ -315,6 +774,539 @@ fn extract_command(input: PathBuf, file_path: String, output: PathBuf) -> Result
     Ok(())
 }
 
+fn info_command(input: PathBuf, format: Format) -> Result<()> {
+    if !input.is_file() {
+        anyhow::bail!("Input path is not a file: {}", input.display());
+    }
+
+    let vpk =
+        VPK::open(&input).with_context(|| format!("Failed to open VPK: {}", input.display()))?;
+    let info = vpk
+        .info()
+        .with_context(|| format!("Failed to inspect VPK: {}", input.display()))?;
+
+    match format {
+        Format::Text => {}
+        Format::Json => return print_json(&info),
+        // One `key,value` row per field, keyed by its path in the JSON output
+        Format::Csv | Format::Tsv => {
+            let mut fields = Vec::new();
+            flatten_json(String::new(), serde_json::to_value(&info)?, &mut fields);
+            return print_table(
+                format,
+                &["key", "value"],
+                fields.into_iter().map(|(key, value)| vec![key, value]),
+            );
+        }
+    }
+
+    let header = &info.header;
+    let optional = |value: Option<u32>| value.map_or("-".to_string(), |value| value.to_string());
+    println!("VPK: {}", info.path.display());
+    println!("File size: {} bytes", info.file_size);
+    println!();
+    println!("Header:");
+    println!("  Signature:            {:#010x}", header.signature);
+    println!("  Version:              {}", header.version as u32);
+    println!("  Header length:        {}", header.header_length);
+    println!("  Tree length:          {}", header.tree_length);
+    println!(
+        "  Embedded data length: {}",
+        optional(header.embed_chunk_length)
+    );
+    println!(
+        "  Archive MD5s length:  {}",
+        optional(header.chunk_hashes_length)
+    );
+    println!(
+        "  Self hashes length:   {}",
+        optional(header.self_hashes_length)
+    );
+    println!(
+        "  Signature length:     {}",
+        optional(header.signature_length)
+    );
+    println!();
+
+    let sections = &info.sections;
+    println!("{:<16} {:>12} {:>12}", "Section", "Offset", "Length");
+    println!("{}", "-".repeat(42));
+    for (name, section) in [
+        ("Tree", sections.tree),
+        ("Embedded data", sections.embedded_data),
+        ("Archive MD5s", sections.chunk_hashes),
+        ("Self hashes", sections.self_hashes),
+        ("Signature", sections.signature),
+    ] {
+        println!("{name:<16} {:>12} {:>12}", section.offset, section.length);
+    }
+    println!("Trailing bytes: {}", info.trailing_bytes);
+    println!();
+
+    if let Some(checksums) = &info.checksums {
+        println!("Checksums:");
+        println!("  Tree:         {}", to_hex(&checksums.tree_checksum));
+        println!(
+            "  Archive MD5s: {}",
+            to_hex(&checksums.chunk_hashes_checksum)
+        );
+        println!("  File:         {}", to_hex(&checksums.file_checksum));
+        println!();
+    }
+
+    println!(
+        "Entries: {} ({} in the directory file)",
+        info.entries, info.embedded_entries
+    );
+    if !info.chunks.is_empty() {
+        println!();
+        println!(
+            "{:<7} {:>8} {:>12} {:>12}  Path",
+            "Archive", "Entries", "Data", "File size"
+        );
+        println!("{}", "-".repeat(60));
+        for chunk in &info.chunks {
+            println!(
+                "{:<7} {:>8} {:>12} {:>12}  {}",
+                format!("{:03}", chunk.index),
+                chunk.entries,
+                chunk.data_length,
+                chunk
+                    .file_size
+                    .map_or("missing".to_string(), |size| size.to_string()),
+                chunk.path.display()
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Location and checksum of an entry, as printed by `list` and `verify`
+#[derive(Serialize)]
+struct EntryRow<'a> {
+    path: &'a str,
+    size: u64,
+    crc32: u32,
+    preload_length: u16,
+    /// Chunk index, or 32767 for data in the directory file
+    archive_index: u16,
+    /// Offset of the data in the chunk, or from the start of the directory file
+    archive_offset: u32,
+}
+
+impl<'a> EntryRow<'a> {
+    const COLUMNS: &'static [&'static str] = &[
+        "path",
+        "size",
+        "crc32",
+        "preload_length",
+        "archive_index",
+        "archive_offset",
+    ];
+
+    fn new(entry: &Entry<'a>) -> Self {
+        EntryRow {
+            path: entry.path(),
+            size: entry.size(),
+            crc32: entry.crc32(),
+            preload_length: entry.preload_length(),
+            archive_index: entry.metadata().archive_index,
+            archive_offset: entry.metadata().archive_offset,
+        }
+    }
+
+    /// Table fields, with the CRC32 in hex as in text output
+    fn fields(&self) -> Vec<String> {
+        vec![
+            self.path.to_string(),
+            self.size.to_string(),
+            format!("{:08x}", self.crc32),
+            self.preload_length.to_string(),
+            self.archive_index.to_string(),
+            self.archive_offset.to_string(),
+        ]
+    }
+}
+
+/// Outcome of checking one entry with `verify`
+#[derive(Serialize)]
+struct VerifyRow<'a> {
+    #[serde(flatten)]
+    entry: EntryRow<'a>,
+    /// `ok`, `mismatch`, `unreadable` or `missing` (its chunk file does not exist)
+    status: &'static str,
+    actual_crc32: Option<u32>,
+    error: Option<&'a str>,
+}
+
+impl<'a> VerifyRow<'a> {
+    const COLUMNS: &'static [&'static str] = &[
+        "path",
+        "size",
+        "crc32",
+        "preload_length",
+        "archive_index",
+        "archive_offset",
+        "status",
+        "actual_crc32",
+        "error",
+    ];
+
+    /// Builds a row for every checked entry from the failures in `report`
+    fn rows(entries: &[Entry<'a>], report: &'a VerifyReport) -> Vec<Self> {
+        let mismatches: HashMap<_, _> = report
+            .mismatches
+            .iter()
+            .map(|mismatch| (mismatch.path.as_str(), mismatch.actual))
+            .collect();
+        let unreadable: HashMap<_, _> = report
+            .unreadable
+            .iter()
+            .map(|unreadable| (unreadable.path.as_str(), unreadable.error.as_str()))
+            .collect();
+        let missing: HashSet<_> = report
+            .missing_chunks
+            .iter()
+            .map(|chunk| chunk.index)
+            .collect();
+
+        entries
+            .iter()
+            .map(|entry| {
+                let mut row = VerifyRow {
+                    entry: EntryRow::new(entry),
+                    status: "ok",
+                    actual_crc32: None,
+                    error: None,
+                };
+                if let Some(&actual) = mismatches.get(entry.path()) {
+                    row.status = "mismatch";
+                    row.actual_crc32 = Some(actual);
+                } else if let Some(&error) = unreadable.get(entry.path()) {
+                    row.status = "unreadable";
+                    row.error = Some(error);
+                } else if entry.metadata().file_length > 0
+                    && missing.contains(&entry.metadata().archive_index)
+                {
+                    row.status = "missing";
+                }
+                row
+            })
+            .collect()
+    }
+
+    fn fields(&self) -> Vec<String> {
+        let mut fields = self.entry.fields();
+        fields.push(self.status.to_string());
+        fields.push(
+            self.actual_crc32
+                .map_or(String::new(), |crc| format!("{crc:08x}")),
+        );
+        fields.push(self.error.unwrap_or_default().to_string());
+        fields
+    }
+}
+
+/// JSON output of `verify`: the report plus a row per checked entry
+#[derive(Serialize)]
+struct VerifyOutput<'a> {
+    #[serde(flatten)]
+    report: &'a VerifyReport,
+    entries: Vec<VerifyRow<'a>>,
+}
+
+fn print_json<T: Serialize + ?Sized>(value: &T) -> Result<()> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+    Ok(())
+}
+
+/// Prints a header row and `rows` as CSV or TSV
+fn print_table<I>(format: Format, columns: &[&str], rows: I) -> Result<()>
+where
+    I: IntoIterator<Item = Vec<String>>,
+{
+    let mut output = io::BufWriter::new(io::stdout().lock());
+    write_table(format, columns, rows, &mut output)?;
+    output.flush()?;
+    Ok(())
+}
+
+/// Writes a header row and `rows` as CSV or TSV.
+///
+/// CSV fields are quoted when needed. TSV fields escape tabs, newlines and
+/// backslashes as `\t`, `\n`, `\r` and `\\`.
+fn write_table<I>(format: Format, columns: &[&str], rows: I, output: &mut dyn Write) -> Result<()>
+where
+    I: IntoIterator<Item = Vec<String>>,
+{
+    let separator = if format == Format::Tsv { "\t" } else { "," };
+    let escape = |field: &str| match format {
+        Format::Tsv => field
+            .replace('\\', "\\\\")
+            .replace('\t', "\\t")
+            .replace('\n', "\\n")
+            .replace('\r', "\\r"),
+        _ if field.contains([',', '"', '\n', '\r']) => {
+            format!("\"{}\"", field.replace('"', "\"\""))
+        }
+        _ => field.to_string(),
+    };
+
+    writeln!(output, "{}", columns.join(separator))?;
+    for row in rows {
+        let row: Vec<_> = row.iter().map(|field| escape(field)).collect();
+        writeln!(output, "{}", row.join(separator))?;
+    }
+    Ok(())
+}
+
+/// Flattens a JSON value into `key,value` pairs, joining object keys and
+/// array indices with dots
+fn flatten_json(key: String, value: serde_json::Value, fields: &mut Vec<(String, String)>) {
+    let child = |name: &dyn std::fmt::Display| {
+        if key.is_empty() {
+            name.to_string()
+        } else {
+            format!("{key}.{name}")
+        }
+    };
+    match value {
+        serde_json::Value::Object(object) => {
+            for (name, value) in object {
+                flatten_json(child(&name), value, fields);
+            }
+        }
+        serde_json::Value::Array(array) => {
+            for (index, value) in array.into_iter().enumerate() {
+                flatten_json(child(&index), value, fields);
+            }
+        }
+        serde_json::Value::Null => fields.push((key, String::new())),
+        serde_json::Value::String(value) => fields.push((key, value)),
+        value => fields.push((key, value.to_string())),
+    }
+}
+
+fn tree_command(input: PathBuf, prefix: &str, depth: Option<usize>) -> Result<()> {
+    if !input.is_file() {
+        anyhow::bail!("Input path is not a file: {}", input.display());
+    }
+
+    let vpk =
+        VPK::open(&input).with_context(|| format!("Failed to open VPK: {}", input.display()))?;
+    let prefix = prefix.replace('\\', "/").trim_matches('/').to_string();
+    let children = vpk.read_dir(&prefix)?;
+    let usage = DiskUsage::from_entries(vpk.walk(&prefix)?);
+
+    if prefix.is_empty() {
+        println!("{} ({})", input.display(), usage_summary(usage.total));
+    } else {
+        println!("{prefix}/ ({})", usage_summary(usage.total));
+    }
+    print_tree(&vpk, &usage, &children, "", depth, 1)?;
+
+    let directories = usage
+        .directories
+        .keys()
+        .filter(|dir| relative_depth(dir, &prefix).is_some_and(|depth| depth > 0))
+        .count();
+    println!();
+    let plural = |count: usize, one: &str, many: &str| {
+        format!("{count} {}", if count == 1 { one } else { many })
+    };
+    println!(
+        "{}, {}",
+        plural(directories, "directory", "directories"),
+        plural(usage.total.files, "file", "files")
+    );
+    Ok(())
+}
+
+/// Prints the children of a directory and, down to `depth`, their contents
+fn print_tree(
+    vpk: &VPK,
+    usage: &DiskUsage,
+    children: &[DirEntry<'_>],
+    indent: &str,
+    depth: Option<usize>,
+    level: usize,
+) -> Result<()> {
+    for (position, child) in children.iter().enumerate() {
+        let last = position + 1 == children.len();
+        let branch = if last { "└── " } else { "├── " };
+        match child {
+            DirEntry::Directory(path) => {
+                let total = usage.directories.get(*path).copied().unwrap_or_default();
+                println!(
+                    "{indent}{branch}{}/ ({})",
+                    child.name(),
+                    usage_summary(total)
+                );
+                if depth.is_none_or(|depth| level < depth) {
+                    let indent = format!("{indent}{}", if last { "    " } else { "│   " });
+                    print_tree(vpk, usage, &vpk.read_dir(path)?, &indent, depth, level + 1)?;
+                }
+            }
+            DirEntry::File(entry) => {
+                println!(
+                    "{indent}{branch}{} ({})",
+                    child.name(),
+                    format_size(entry.size())
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+fn du_command(input: PathBuf, prefix: &str, depth: usize, top: usize) -> Result<()> {
+    if !input.is_file() {
+        anyhow::bail!("Input path is not a file: {}", input.display());
+    }
+
+    let vpk =
+        VPK::open(&input).with_context(|| format!("Failed to open VPK: {}", input.display()))?;
+    let prefix = prefix.replace('\\', "/").trim_matches('/').to_string();
+    let entries: Vec<_> = vpk.walk(&prefix)?.collect();
+    let usage = DiskUsage::from_entries(entries.iter().copied());
+
+    println!("Total: {}", usage_summary(usage.total));
+
+    let by_size = |a: &(String, UsageTotal), b: &(String, UsageTotal)| {
+        b.1.bytes.cmp(&a.1.bytes).then_with(|| a.0.cmp(&b.0))
+    };
+    let print_totals = |column: &str, mut totals: Vec<(String, UsageTotal)>, sort: bool| {
+        if sort {
+            totals.sort_by(by_size);
+        }
+        println!();
+        println!("{:>10} {:>8}  {column}", "Size", "Files");
+        for (name, total) in totals {
+            println!(
+                "{:>10} {:>8}  {name}",
+                format_size(total.bytes),
+                total.files
+            );
+        }
+    };
+
+    let directories = usage
+        .directories
+        .iter()
+        .filter(|(dir, _)| relative_depth(dir, &prefix).is_some_and(|d| (1..=depth).contains(&d)))
+        .map(|(dir, total)| (format!("{dir}/"), *total))
+        .collect();
+    print_totals("Directory", directories, true);
+
+    let extensions = usage
+        .extensions
+        .iter()
+        .map(|(ext, total)| {
+            let name = if ext.is_empty() {
+                "(none)".to_string()
+            } else {
+                format!(".{ext}")
+            };
+            (name, *total)
+        })
+        .collect();
+    print_totals("Extension", extensions, true);
+
+    let archives = usage
+        .archives
+        .iter()
+        .map(|(&index, total)| {
+            let name = if index == EMBEDDED_ARCHIVE_INDEX {
+                "directory file".to_string()
+            } else {
+                format!("{index:03}")
+            };
+            (name, *total)
+        })
+        .collect();
+    print_totals("Archive", archives, false);
+
+    if top > 0 {
+        let mut largest = entries;
+        largest.sort_by(|a, b| b.size().cmp(&a.size()).then_with(|| a.path().cmp(b.path())));
+        println!();
+        println!("{:>10}  Largest files", "Size");
+        for entry in largest.iter().take(top) {
+            println!("{:>10}  {}", format_size(entry.size()), entry.path());
+        }
+    }
+
+    Ok(())
+}
+
+/// Depth of `dir` below `prefix`: 0 for `prefix` itself, `None` outside it
+fn relative_depth(dir: &str, prefix: &str) -> Option<usize> {
+    let rest = if prefix.is_empty() {
+        dir
+    } else {
+        let rest = dir.strip_prefix(prefix)?;
+        if !rest.is_empty() && !rest.starts_with('/') {
+            return None;
+        }
+        rest.trim_start_matches('/')
+    };
+    Some(if rest.is_empty() {
+        0
+    } else {
+        rest.split('/').count()
+    })
+}
+
+fn usage_summary(total: UsageTotal) -> String {
+    let files = if total.files == 1 { "file" } else { "files" };
+    format!("{} {files}, {}", total.files, format_size(total.bytes))
+}
+
+/// Formats a byte count with binary units, e.g. `1.5 MiB`
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{size:.1} {}", UNITS[unit])
+}
+
+fn cat_command(input: PathBuf, paths: &[String], output: &mut dyn Write) -> Result<()> {
+    if !input.is_file() {
+        anyhow::bail!("Input path is not a file: {}", input.display());
+    }
+
+    let vpk =
+        VPK::open(&input).with_context(|| format!("Failed to open VPK: {}", input.display()))?;
+
+    // Check every path first so nothing is written for a mistyped one
+    for path in paths {
+        if !vpk.contains(path) {
+            anyhow::bail!("File not found in VPK: {}", path);
+        }
+    }
+
+    write_entries(&vpk, paths, output)
+}
+
+/// Writes the raw contents of entries to `output`, one after another
+fn write_entries(vpk: &VPK, paths: &[String], output: &mut dyn Write) -> Result<()> {
+    let mut output = io::BufWriter::new(output);
+    for path in paths {
+        vpk.copy_to(path, &mut output)
+            .with_context(|| format!("Failed to write file: {path}"))?;
+    }
+    output.flush().context("Failed to write to stdout")?;
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -338,11 +1330,23 @@ mod tests {
         )?;
 
         // Pack
-        pack_command(src_dir.clone(), vpk_path.clone(), false)?;
+        pack_command(
+            &[parse_mount(src_dir.to_str().unwrap()).unwrap()],
+            vpk_path.clone(),
+            false,
+            &PackOptions::default(),
+            None,
+        )?;
         assert!(vpk_path.exists());
 
         // Unpack
-        unpack_command(vpk_path, extract_dir.clone(), false)?;
+        unpack_command(
+            vpk_path,
+            extract_dir.clone(),
+            false,
+            &ExtractOptions::default(),
+            &PathFilter::default(),
+        )?;
 
         // Verify extracted files
         assert_eq!(
@@ -354,6 +1358,79 @@ mod tests {
             "Nested file content"
         );
 
+        // Cat
+        let mut output = Vec::new();
+        cat_command(
+            temp_dir.path().join("test.vpk"),
+            &["test.txt".to_string(), "subdir/nested.dat".to_string()],
+            &mut output,
+        )?;
+        assert_eq!(output, b"Hello, World!Nested file content");
+        assert!(
+            cat_command(
+                temp_dir.path().join("test.vpk"),
+                &["missing.txt".to_string()],
+                &mut Vec::new(),
+            )
+            .is_err()
+        );
+
         Ok(())
     }
+
+    #[test]
+    fn test_table_formats() -> Result<()> {
+        let rows = || {
+            vec![
+                vec!["plain".to_string(), "1".to_string()],
+                vec!["a,\"b\"".to_string(), "tab\there\\".to_string()],
+            ]
+        };
+
+        let mut csv = Vec::new();
+        write_table(Format::Csv, &["path", "size"], rows(), &mut csv)?;
+        assert_eq!(
+            String::from_utf8(csv)?,
+            "path,size\nplain,1\n\"a,\"\"b\"\"\",tab\there\\\n"
+        );
+
+        let mut tsv = Vec::new();
+        write_table(Format::Tsv, &["path", "size"], rows(), &mut tsv)?;
+        assert_eq!(
+            String::from_utf8(tsv)?,
+            "path\tsize\nplain\t1\na,\"b\"\ttab\\there\\\\\n"
+        );
+
+        let mut fields = Vec::new();
+        flatten_json(
+            String::new(),
+            serde_json::json!({"header": {"version": 2}, "chunks": [{"size": null}]}),
+            &mut fields,
+        );
+        assert_eq!(
+            fields,
+            [
+                ("chunks.0.size".to_string(), String::new()),
+                ("header.version".to_string(), "2".to_string()),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_size_helpers() {
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(1536), "1.5 KiB");
+        assert_eq!(format_size(3 * 1024 * 1024 * 1024), "3.0 GiB");
+
+        assert_eq!(relative_depth("materials", ""), Some(1));
+        assert_eq!(
+            relative_depth("materials/models/props", "materials"),
+            Some(2)
+        );
+        assert_eq!(relative_depth("materials", "materials"), Some(0));
+        assert_eq!(relative_depth("materials_old", "materials"), None);
+        assert_eq!(relative_depth("", "materials"), None);
+    }
 }
@@ -0,0 +1,224 @@
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::error::{IoResultExt, Result, VpkError};
+use crate::pack::{PackOptions, TreeBuilder, unsaved_metadata};
+use crate::utils::{EMBEDDED_ARCHIVE_INDEX, check_archive_path, normalize_path, split_filename};
+use crate::vpk::VPK;
+
+/// Separator between the source path and the archive path in text manifests
+pub const TEXT_MAPPING_SEPARATOR: &str = "=>";
+
+/// One file to pack, with where it goes in the archive
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ManifestEntry {
+    /// File on disk; relative paths are resolved against the manifest's directory
+    pub source: PathBuf,
+    /// Path inside the VPK, using `/` as the separator
+    pub path: String,
+    /// Number of leading bytes to store in the tree instead of the data area
+    #[serde(default)]
+    pub preload: u16,
+    /// Archive chunk (`name_NNN.vpk`) to store the data in; embedded in the
+    /// directory file when `None`
+    #[serde(default)]
+    pub archive: Option<u16>,
+}
+
+impl ManifestEntry {
+    /// Creates an entry that packs `source` at `path` without hints
+    pub fn new(source: impl Into<PathBuf>, path: impl Into<String>) -> Self {
+        ManifestEntry {
+            source: source.into(),
+            path: path.into(),
+            preload: 0,
+            archive: None,
+        }
+    }
+}
+
+/// An explicit list of files to pack, as an alternative to walking a directory.
+///
+/// Text manifests have one file per line, either as a bare path that is used
+/// for both the source and the archive path, or as
+/// `source/path => archive/path`. Blank lines and lines starting with `#`
+/// are skipped.
+///
+/// JSON manifests are an array of [`ManifestEntry`] objects, or an object
+/// with a `files` array, and can also set `preload` and `archive` hints:
+///
+/// ```json
+/// { "files": [{ "source": "build/ui.res", "path": "resource/ui.res", "preload": 64 }] }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Manifest {
+    pub entries: Vec<ManifestEntry>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum JsonManifest {
+    List(Vec<ManifestEntry>),
+    Object { files: Vec<ManifestEntry> },
+}
+
+impl Manifest {
+    /// Reads a manifest file, parsing it as JSON if it has a `.json`
+    /// extension and as text otherwise
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path)
+            .io_context(|| format!("Failed to read manifest: {}", path.display()))?;
+        let base_dir = path.parent().unwrap_or(Path::new(""));
+
+        let is_json = path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+        if is_json {
+            Self::parse_json(&contents, base_dir)
+        } else {
+            Self::parse_text(&contents, base_dir)
+        }
+    }
+
+    /// Parses a text manifest, resolving relative sources against `base_dir`
+    pub fn parse_text(text: &str, base_dir: &Path) -> Result<Self> {
+        let mut entries = Vec::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (source, path) = match line.split_once(TEXT_MAPPING_SEPARATOR) {
+                Some((source, path)) => (source.trim(), path.trim()),
+                None => (line, line),
+            };
+            if source.is_empty() || path.is_empty() {
+                return Err(VpkError::InvalidManifest(format!(
+                    "line {}: expected `source` or `source => path`",
+                    number + 1
+                )));
+            }
+            entries.push(ManifestEntry::new(base_dir.join(source), path));
+        }
+        Ok(Manifest { entries })
+    }
+
+    /// Parses a JSON manifest, resolving relative sources against `base_dir`
+    pub fn parse_json(text: &str, base_dir: &Path) -> Result<Self> {
+        let files = match serde_json::from_str(text) {
+            Ok(JsonManifest::List(files) | JsonManifest::Object { files }) => files,
+            Err(e) => return Err(VpkError::InvalidManifest(e.to_string())),
+        };
+        let entries = files
+            .into_iter()
+            .map(|entry| ManifestEntry {
+                source: base_dir.join(&entry.source),
+                ..entry
+            })
+            .collect();
+        Ok(Manifest { entries })
+    }
+}
+
+impl PackOptions {
+    /// Creates a new VPK from the files listed in a manifest.
+    ///
+    /// The filter and conflict policy apply to archive paths; `.vpkignore`
+    /// files do not apply, since every file is listed explicitly.
+    pub fn pack_manifest(&self, manifest: &Manifest) -> Result<VPK> {
+        let mut tree = TreeBuilder::new(self.conflict_policy);
+
+        for entry in &manifest.entries {
+            let path = normalize_path(&entry.path)
+                .trim_start_matches('/')
+                .to_string();
+            if !self.filter.is_match(&path) {
+                continue;
+            }
+
+            let file_name = path
+                .rsplit_once('/')
+                .map_or(path.as_str(), |(_, name)| name);
+            split_filename(file_name)?;
+            check_archive_path(&path).map_err(|reason| {
+                VpkError::InvalidManifest(format!("archive path {} {reason}", entry.path))
+            })?;
+            if entry
+                .archive
+                .is_some_and(|index| index >= EMBEDDED_ARCHIVE_INDEX)
+            {
+                return Err(VpkError::InvalidManifest(format!(
+                    "archive index of {} must be below {EMBEDDED_ARCHIVE_INDEX}",
+                    entry.path
+                )));
+            }
+
+            if !tree.claim(&path, &entry.source)? {
+                continue;
+            }
+            let data = std::fs::read(&entry.source)
+                .io_context(|| format!("Failed to read file: {}", entry.source.display()))?;
+            let mut metadata = unsaved_metadata(data, None);
+            metadata.preload_length = (entry.preload as u32).min(metadata.file_length) as u16;
+            metadata.file_length -= metadata.preload_length as u32;
+            metadata.archive_index = entry.archive.unwrap_or(EMBEDDED_ARCHIVE_INDEX);
+
+            tree.insert(path, metadata);
+        }
+
+        VPK::from_tree(tree.finish())
+    }
+}
+
+impl VPK {
+    /// Creates a new VPK from a text or JSON manifest file
+    pub fn from_manifest<P: AsRef<Path>>(path: P) -> Result<Self> {
+        PackOptions::new().pack_manifest(&Manifest::from_path(path)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_manifests() -> Result<()> {
+        let base = Path::new("base");
+
+        let text = "# shipped files\n\nscripts/a.lua\nbuild/b.vmt => materials/b.vmt\n";
+        let manifest = Manifest::parse_text(text, base)?;
+        assert_eq!(
+            manifest.entries,
+            [
+                ManifestEntry::new("base/scripts/a.lua", "scripts/a.lua"),
+                ManifestEntry::new("base/build/b.vmt", "materials/b.vmt"),
+            ]
+        );
+        assert!(Manifest::parse_text("a.txt =>", base).is_err());
+
+        let json =
+            r#"[{"source": "b.vmt", "path": "materials/b.vmt", "preload": 16, "archive": 1}]"#;
+        let manifest = Manifest::parse_json(json, base)?;
+        assert_eq!(manifest.entries[0].source, Path::new("base/b.vmt"));
+        assert_eq!(manifest.entries[0].preload, 16);
+        assert_eq!(manifest.entries[0].archive, Some(1));
+
+        let json = r#"{"files": [{"source": "/abs/a.txt", "path": "a.txt"}]}"#;
+        let manifest = Manifest::parse_json(json, base)?;
+        assert_eq!(
+            manifest.entries[0],
+            ManifestEntry::new("/abs/a.txt", "a.txt")
+        );
+
+        assert!(matches!(
+            Manifest::parse_json(r#"[{"source": "a.txt"}]"#, base),
+            Err(VpkError::InvalidManifest(_))
+        ));
+
+        Ok(())
+    }
+}
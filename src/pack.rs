@@ -0,0 +1,254 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use ignore::WalkBuilder;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+
+use crate::error::{IoResultExt, Result, VpkError};
+use crate::file::FileMetadata;
+use crate::filter::PathFilter;
+use crate::utils::{
+    EMBEDDED_ARCHIVE_INDEX, NameEncoding, check_archive_path, decode_name, normalize_path,
+    path_to_entry_bytes, split_filename,
+};
+use crate::vpk::VPK;
+
+/// Name of the files holding ignore rules inside a packed directory
+pub const IGNORE_FILE_NAME: &str = ".vpkignore";
+
+/// What to do when two source files map to the same archive path
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ConflictPolicy {
+    /// Fail with [`VpkError::PathConflict`]
+    #[default]
+    Error,
+    /// Keep the file that was added first
+    FirstWins,
+    /// Replace earlier files with the one added last
+    LastWins,
+}
+
+/// Collects the entries of a VPK being packed, applying a conflict policy
+pub(crate) struct TreeBuilder {
+    policy: ConflictPolicy,
+    entries: HashMap<String, FileMetadata>,
+    sources: HashMap<String, PathBuf>,
+}
+
+impl TreeBuilder {
+    pub(crate) fn new(policy: ConflictPolicy) -> Self {
+        TreeBuilder {
+            policy,
+            entries: HashMap::new(),
+            sources: HashMap::new(),
+        }
+    }
+
+    /// Records `source` as the file for `path` and returns whether it should
+    /// be read and inserted, according to the conflict policy
+    pub(crate) fn claim(&mut self, path: &str, source: &Path) -> Result<bool> {
+        match self.sources.get(path) {
+            None => {}
+            Some(_) if self.policy == ConflictPolicy::FirstWins => return Ok(false),
+            Some(first) if self.policy == ConflictPolicy::Error => {
+                return Err(VpkError::PathConflict {
+                    path: path.to_string(),
+                    first: first.clone(),
+                    second: source.to_path_buf(),
+                });
+            }
+            Some(_) => {}
+        }
+        self.sources.insert(path.to_string(), source.to_path_buf());
+        Ok(true)
+    }
+
+    /// Adds an entry claimed with [`TreeBuilder::claim`]
+    pub(crate) fn insert(&mut self, path: String, metadata: FileMetadata) {
+        self.entries.insert(path, metadata);
+    }
+
+    pub(crate) fn finish(self) -> HashMap<String, FileMetadata> {
+        self.entries
+    }
+}
+
+/// Options controlling how a VPK is built from files on disk
+#[derive(Debug, Clone)]
+pub struct PackOptions {
+    pub(crate) filter: PathFilter,
+    use_ignore_files: bool,
+    ignore_rules: Vec<String>,
+    pub(crate) conflict_policy: ConflictPolicy,
+}
+
+impl Default for PackOptions {
+    fn default() -> Self {
+        PackOptions {
+            filter: PathFilter::default(),
+            use_ignore_files: true,
+            ignore_rules: Vec::new(),
+            conflict_policy: ConflictPolicy::default(),
+        }
+    }
+}
+
+impl PackOptions {
+    /// Creates the default options, which pack every file not excluded by a
+    /// `.vpkignore` file
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets whether `.vpkignore` files are honoured.
+    ///
+    /// They use gitignore syntax and apply to the directory they are in and
+    /// everything below it, with rules in deeper files taking precedence.
+    /// The `.vpkignore` files themselves are never packed while this is on.
+    pub fn use_ignore_files(mut self, use_ignore_files: bool) -> Self {
+        self.use_ignore_files = use_ignore_files;
+        self
+    }
+
+    /// Adds gitignore-style rules relative to the packed directory, applied
+    /// whether or not `.vpkignore` files are honoured
+    pub fn ignore_rules<I, S>(mut self, rules: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.ignore_rules.extend(rules.into_iter().map(Into::into));
+        self
+    }
+
+    /// Only packs files whose path relative to the source directory is
+    /// selected by `filter`
+    pub fn filter(mut self, filter: PathFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// Sets what happens when two source files map to the same archive path
+    pub fn conflict_policy(mut self, policy: ConflictPolicy) -> Self {
+        self.conflict_policy = policy;
+        self
+    }
+
+    /// Creates a new VPK from a directory structure
+    pub fn pack_directory<P: AsRef<Path>>(&self, path: P) -> Result<VPK> {
+        self.pack_roots([(path, "")])
+    }
+
+    /// Creates a new VPK from several directories, each mounted at a prefix
+    /// inside the archive, e.g. `("build/materials", "materials")`.
+    ///
+    /// Roots are packed in order, which decides the winner of a conflict
+    /// under [`ConflictPolicy::FirstWins`] and [`ConflictPolicy::LastWins`].
+    /// Ignore files and rules apply relative to each root, the filter to
+    /// the mounted archive paths.
+    pub fn pack_roots<I, P, S>(&self, roots: I) -> Result<VPK>
+    where
+        I: IntoIterator<Item = (P, S)>,
+        P: AsRef<Path>,
+        S: AsRef<str>,
+    {
+        let mut tree = TreeBuilder::new(self.conflict_policy);
+        for (root, prefix) in roots {
+            self.pack_root(&mut tree, root.as_ref(), prefix.as_ref())?;
+        }
+        VPK::from_tree(tree.finish())
+    }
+
+    /// Walks one root directory and adds its files below `prefix`
+    fn pack_root(&self, tree: &mut TreeBuilder, path: &Path, prefix: &str) -> Result<()> {
+        if !path.is_dir() {
+            return Err(VpkError::NotADirectory(path.to_path_buf()));
+        }
+        let prefix = normalize_path(prefix).trim_matches('/').to_string();
+        if !prefix.is_empty() {
+            check_archive_path(&prefix).map_err(|reason| VpkError::UnsafePath {
+                path: prefix.clone(),
+                reason,
+            })?;
+        }
+
+        let extra_rules = self.compile_ignore_rules(path)?;
+        let mut walker = WalkBuilder::new(path);
+        walker.standard_filters(false);
+        if self.use_ignore_files {
+            walker.add_custom_ignore_filename(IGNORE_FILE_NAME);
+        }
+        walker.filter_entry(move |entry| {
+            let is_dir = entry.file_type().is_some_and(|t| t.is_dir());
+            !extra_rules.matched(entry.path(), is_dir).is_ignore()
+        });
+
+        // Walk the directory and build the file tree
+        for entry in walker.build().filter_map(|e| e.ok()) {
+            if self.use_ignore_files && entry.file_name() == IGNORE_FILE_NAME {
+                continue;
+            }
+            if entry.file_type().is_some_and(|t| t.is_file()) {
+                let relative_path = entry
+                    .path()
+                    .strip_prefix(path)
+                    .expect("walked entries are inside the root directory");
+
+                // Keep the exact bytes of names that are not valid UTF-8
+                let mut raw_path = path_to_entry_bytes(relative_path);
+                if !prefix.is_empty() {
+                    raw_path.splice(0..0, format!("{prefix}/").into_bytes());
+                }
+                let path_str = decode_name(&raw_path, NameEncoding::Windows1252)
+                    .expect("Windows-1252 decoding cannot fail")
+                    .into_owned();
+                if !self.filter.is_match(&path_str) {
+                    continue;
+                }
+                let raw_path = (raw_path != path_str.as_bytes()).then_some(raw_path);
+                let (_name, _ext) = split_filename(&entry.file_name().to_string_lossy())?;
+
+                if !tree.claim(&path_str, entry.path())? {
+                    continue;
+                }
+                let file_data = std::fs::read(entry.path())
+                    .io_context(|| format!("Failed to read file: {}", entry.path().display()))?;
+
+                tree.insert(path_str, unsaved_metadata(file_data, raw_path));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Compiles the extra ignore rules against the packed directory
+    fn compile_ignore_rules(&self, root: &Path) -> Result<Gitignore> {
+        let mut builder = GitignoreBuilder::new(root);
+        for rule in &self.ignore_rules {
+            builder
+                .add_line(None, rule)
+                .map_err(|e| VpkError::InvalidPattern {
+                    pattern: rule.clone(),
+                    reason: e.to_string(),
+                })?;
+        }
+        builder.build().map_err(|e| VpkError::InvalidPattern {
+            pattern: self.ignore_rules.join(", "),
+            reason: e.to_string(),
+        })
+    }
+}
+
+/// Builds the metadata of a file that is held in memory until the VPK is saved
+pub(crate) fn unsaved_metadata(data: Vec<u8>, raw_path: Option<Vec<u8>>) -> FileMetadata {
+    // For now, we embed all files (no separate archive files)
+    FileMetadata {
+        crc32: crc32fast::hash(&data),
+        preload_length: 0,
+        archive_index: EMBEDDED_ARCHIVE_INDEX,
+        archive_offset: 0, // Will be set during save
+        file_length: data.len() as u32,
+        preload: data,
+        raw_path,
+    }
+}
@@ -0,0 +1,89 @@
+//! Worker pool shared by the operations that process many entries at once
+
+use std::num::NonZeroUsize;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+use crate::entry::Entry;
+use crate::error::Result;
+
+/// Resolves a requested number of threads, where `0` means one per
+/// available CPU, without starting more threads than there are `work` items
+pub(crate) fn thread_count(jobs: usize, work: usize) -> usize {
+    let jobs = match jobs {
+        0 => thread::available_parallelism().map_or(1, NonZeroUsize::get),
+        jobs => jobs,
+    };
+    jobs.min(work).max(1)
+}
+
+/// Sort key that puts entries in the order their data is stored on disk
+pub(crate) fn read_order(entry: &Entry<'_>) -> (u16, u32) {
+    let metadata = entry.metadata();
+    (metadata.archive_index, metadata.archive_offset)
+}
+
+/// Splits items sorted by [`read_order`] into batches that never span two
+/// archive chunks, small enough that `threads` workers stay busy until the end
+pub(crate) fn split_batches<T>(
+    items: &[T],
+    threads: usize,
+    archive_index: impl Fn(&T) -> u16,
+) -> Vec<&[T]> {
+    let max_batch = items.len().div_ceil(threads * 4).max(1);
+    items
+        .chunk_by(|a, b| archive_index(a) == archive_index(b))
+        .flat_map(|chunk| chunk.chunks(max_batch))
+        .collect()
+}
+
+/// Runs `work` on every batch using `threads` workers and returns the results
+/// in batch order.
+///
+/// The first error stops the workers from starting new batches and is returned.
+pub(crate) fn run_batches<B, R, F>(batches: &[B], threads: usize, work: F) -> Result<Vec<R>>
+where
+    B: Sync,
+    R: Send,
+    F: Fn(&B) -> Result<R> + Sync,
+{
+    let next_batch = AtomicUsize::new(0);
+    let first_error = Mutex::new(None);
+    let results = Mutex::new(Vec::with_capacity(batches.len()));
+
+    thread::scope(|scope| {
+        for _ in 0..threads {
+            scope.spawn(|| {
+                loop {
+                    let index = next_batch.fetch_add(1, Ordering::Relaxed);
+                    let Some(batch) = batches.get(index) else {
+                        break;
+                    };
+                    match work(batch) {
+                        Ok(result) => results
+                            .lock()
+                            .expect("batch results lock poisoned")
+                            .push((index, result)),
+                        Err(e) => {
+                            first_error
+                                .lock()
+                                .expect("batch error lock poisoned")
+                                .get_or_insert(e);
+                            // Skip the remaining batches
+                            next_batch.store(batches.len(), Ordering::Relaxed);
+                            break;
+                        }
+                    }
+                }
+            });
+        }
+    });
+
+    if let Some(e) = first_error.into_inner().expect("batch error lock poisoned") {
+        return Err(e);
+    }
+    let mut results = results.into_inner().expect("batch results lock poisoned");
+    results.sort_by_key(|(index, _)| *index);
+    Ok(results.into_iter().map(|(_, result)| result).collect())
+}
@@ -0,0 +1,117 @@
+use std::ops::ControlFlow;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+
+use crate::error::{Result, VpkError};
+
+/// A long-running operation that reports progress
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Operation {
+    /// Writing a VPK and its archive chunks
+    Save,
+    /// Extracting entries to disk
+    Extract,
+    /// Checking entry CRCs
+    Verify,
+}
+
+/// Progress of an operation after one more file was processed
+#[derive(Debug, Clone, Copy)]
+pub struct ProgressEvent<'a> {
+    pub operation: Operation,
+    /// Entry path of the file that was just processed
+    pub path: &'a str,
+    pub files_done: usize,
+    pub files_total: usize,
+    /// Entry bytes processed so far, including preload bytes
+    pub bytes_done: u64,
+    pub bytes_total: u64,
+}
+
+/// Receives progress updates from [`VPK::save_with_progress`](crate::VPK::save_with_progress),
+/// [`VPK::extract_entries`](crate::VPK::extract_entries) and
+/// [`VPK::verify_entries`](crate::VPK::verify_entries).
+///
+/// Parallel operations call [`Progress::update`] from their worker threads,
+/// so events can arrive slightly out of order.
+pub trait Progress: Send + Sync {
+    /// Called after each file. Returning [`ControlFlow::Break`] cancels the
+    /// operation, which then fails with [`VpkError::Cancelled`]; files
+    /// already written are left in place.
+    fn update(&self, event: &ProgressEvent<'_>) -> ControlFlow<()>;
+}
+
+impl<F> Progress for F
+where
+    F: Fn(&ProgressEvent<'_>) -> ControlFlow<()> + Send + Sync,
+{
+    fn update(&self, event: &ProgressEvent<'_>) -> ControlFlow<()> {
+        self(event)
+    }
+}
+
+/// An optional [`Progress`] observer held by option structs
+#[derive(Clone, Default)]
+pub(crate) struct SharedProgress(pub(crate) Option<Arc<dyn Progress>>);
+
+impl std::fmt::Debug for SharedProgress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(if self.0.is_some() { "Some(..)" } else { "None" })
+    }
+}
+
+/// Counts the files and bytes of one operation and forwards them to an observer
+pub(crate) struct ProgressTracker<'a> {
+    progress: Option<&'a dyn Progress>,
+    operation: Operation,
+    files_total: usize,
+    bytes_total: u64,
+    files_done: AtomicUsize,
+    bytes_done: AtomicU64,
+    cancelled: AtomicBool,
+}
+
+impl<'a> ProgressTracker<'a> {
+    pub(crate) fn new(
+        progress: Option<&'a dyn Progress>,
+        operation: Operation,
+        files_total: usize,
+        bytes_total: u64,
+    ) -> Self {
+        ProgressTracker {
+            progress,
+            operation,
+            files_total,
+            bytes_total,
+            files_done: AtomicUsize::new(0),
+            bytes_done: AtomicU64::new(0),
+            cancelled: AtomicBool::new(false),
+        }
+    }
+
+    /// Records a processed file, failing with [`VpkError::Cancelled`] once
+    /// the observer has asked to stop
+    pub(crate) fn file_done(&self, path: &str, bytes: u64) -> Result<()> {
+        let Some(progress) = self.progress else {
+            return Ok(());
+        };
+        if self.cancelled.load(Ordering::Relaxed) {
+            return Err(VpkError::Cancelled);
+        }
+
+        let event = ProgressEvent {
+            operation: self.operation,
+            path,
+            files_done: self.files_done.fetch_add(1, Ordering::Relaxed) + 1,
+            files_total: self.files_total,
+            bytes_done: self.bytes_done.fetch_add(bytes, Ordering::Relaxed) + bytes,
+            bytes_total: self.bytes_total,
+        };
+        if progress.update(&event).is_break() {
+            self.cancelled.store(true, Ordering::Relaxed);
+            return Err(VpkError::Cancelled);
+        }
+        Ok(())
+    }
+}
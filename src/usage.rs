@@ -0,0 +1,114 @@
+use std::collections::BTreeMap;
+
+use crate::entry::Entry;
+
+/// Number of files and bytes in a group of entries
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct UsageTotal {
+    pub files: usize,
+    /// Entry sizes, including preload bytes
+    pub bytes: u64,
+}
+
+impl UsageTotal {
+    fn add(&mut self, entry: &Entry<'_>) {
+        self.files += 1;
+        self.bytes += entry.size();
+    }
+}
+
+/// Entry sizes summed per directory, extension and archive chunk
+#[derive(Debug, Clone, Default)]
+pub struct DiskUsage {
+    pub total: UsageTotal,
+    /// Totals per directory path (`""` for the root), counting files in
+    /// subdirectories too
+    pub directories: BTreeMap<String, UsageTotal>,
+    /// Totals per extension, without the leading dot (`""` for none)
+    pub extensions: BTreeMap<String, UsageTotal>,
+    /// Totals per archive index, where
+    /// [`EMBEDDED_ARCHIVE_INDEX`](crate::utils::EMBEDDED_ARCHIVE_INDEX) is
+    /// the directory file
+    pub archives: BTreeMap<u16, UsageTotal>,
+}
+
+impl DiskUsage {
+    /// Sums the sizes of `entries`, such as those from
+    /// [`VPK::walk`](crate::VPK::walk) or [`VPK::filtered`](crate::VPK::filtered)
+    pub fn from_entries<'a>(entries: impl IntoIterator<Item = Entry<'a>>) -> Self {
+        let mut usage = DiskUsage::default();
+
+        for entry in entries {
+            usage.total.add(&entry);
+            usage
+                .extensions
+                .entry(entry.extension().to_string())
+                .or_default()
+                .add(&entry);
+            usage
+                .archives
+                .entry(entry.metadata().archive_index)
+                .or_default()
+                .add(&entry);
+
+            let mut dir = entry.directory();
+            loop {
+                usage
+                    .directories
+                    .entry(dir.to_string())
+                    .or_default()
+                    .add(&entry);
+                if dir.is_empty() {
+                    break;
+                }
+                dir = dir.rsplit_once('/').map_or("", |(parent, _)| parent);
+            }
+        }
+
+        usage
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file::FileMetadata;
+    use crate::utils::EMBEDDED_ARCHIVE_INDEX;
+
+    fn metadata(size: u32, archive_index: u16) -> FileMetadata {
+        FileMetadata {
+            preload: Vec::new(),
+            crc32: 0,
+            preload_length: 0,
+            archive_index,
+            archive_offset: 0,
+            file_length: size,
+            raw_path: None,
+        }
+    }
+
+    #[test]
+    fn test_disk_usage() {
+        let files = [
+            ("readme.txt", metadata(10, EMBEDDED_ARCHIVE_INDEX)),
+            ("materials/a.vmt", metadata(100, 0)),
+            ("materials/models/b.vmt", metadata(1000, 0)),
+            ("materials/models/c.vtf", metadata(5000, 1)),
+        ];
+        let usage = DiskUsage::from_entries(
+            files
+                .iter()
+                .map(|(path, metadata)| Entry::new(path, metadata)),
+        );
+
+        let total = |files, bytes| UsageTotal { files, bytes };
+        assert_eq!(usage.total, total(4, 6110));
+        assert_eq!(usage.directories[""], total(4, 6110));
+        assert_eq!(usage.directories["materials"], total(3, 6100));
+        assert_eq!(usage.directories["materials/models"], total(2, 6000));
+        assert_eq!(usage.directories.len(), 3);
+        assert_eq!(usage.extensions["vmt"], total(2, 1100));
+        assert_eq!(usage.archives[&0], total(2, 1100));
+        assert_eq!(usage.archives[&EMBEDDED_ARCHIVE_INDEX], total(1, 10));
+    }
+}
@@ -1,5 +1,9 @@
-use anyhow::{Context, Result};
+use std::borrow::Cow;
 use std::io::{BufRead, Read};
+use std::path::Path;
+use std::str::Utf8Error;
+
+use crate::error::{IoResultExt, Result, VpkError};
 
 /// VPK magic signature
 pub const VPK_SIGNATURE: u32 = 0x55aa1234;
@@ -18,7 +22,7 @@ pub fn read_cstring<R: Read>(reader: &mut R) -> Result<String> {
     loop {
         reader
             .read_exact(&mut byte)
-            .context("Failed to read byte for cstring")?;
+            .io_context(|| "Failed to read byte for cstring")?;
 
         if byte[0] == 0 {
             break;
@@ -27,7 +31,7 @@ pub fn read_cstring<R: Read>(reader: &mut R) -> Result<String> {
         buffer.push(byte[0]);
     }
 
-    String::from_utf8(buffer).context("Invalid UTF-8 in cstring")
+    cstring_from_bytes(buffer)
 }
 
 /// Reads a null-terminated string from a buffered reader (more efficient)
@@ -36,24 +40,37 @@ pub fn read_cstring_buffered<R: BufRead>(reader: &mut R) -> Result<String> {
 
     reader
         .read_until(0, &mut buffer)
-        .context("Failed to read cstring")?;
+        .io_context(|| "Failed to read cstring")?;
 
     // Remove the null terminator
     if buffer.last() == Some(&0) {
         buffer.pop();
     }
 
-    String::from_utf8(buffer).context("Invalid UTF-8 in cstring")
+    cstring_from_bytes(buffer)
+}
+
+/// Converts the bytes of a cstring to a `String`
+fn cstring_from_bytes(buffer: Vec<u8>) -> Result<String> {
+    String::from_utf8(buffer).map_err(|e| VpkError::InvalidUtf8 {
+        what: "cstring".to_string(),
+        source: e.utf8_error(),
+    })
 }
 
 /// Writes a null-terminated string to the writer
 pub fn write_cstring<W: std::io::Write>(writer: &mut W, s: &str) -> Result<()> {
+    write_cstring_bytes(writer, s.as_bytes())
+}
+
+/// Writes raw bytes followed by a null terminator to the writer
+pub fn write_cstring_bytes<W: std::io::Write>(writer: &mut W, bytes: &[u8]) -> Result<()> {
     writer
-        .write_all(s.as_bytes())
-        .context("Failed to write string")?;
+        .write_all(bytes)
+        .io_context(|| "Failed to write string")?;
     writer
         .write_all(&[0])
-        .context("Failed to write null terminator")?;
+        .io_context(|| "Failed to write null terminator")?;
     Ok(())
 }
 
@@ -62,11 +79,117 @@ pub fn cstring_length(s: &str) -> usize {
     s.len() + 1
 }
 
+/// How entry names that are not valid UTF-8 are decoded.
+///
+/// Names that are valid UTF-8 are always used as is; the encoding only
+/// applies to the rest. The original bytes are kept either way and written
+/// back unchanged when the VPK is saved.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NameEncoding {
+    /// Fail with [`VpkError::InvalidUtf8`]
+    Utf8,
+    /// Decode as Windows-1252, the default code page of older Windows tools
+    #[default]
+    Windows1252,
+    /// Decode as ISO-8859-1
+    Latin1,
+    /// Replace invalid sequences with U+FFFD; distinct names may collide
+    Lossy,
+}
+
+/// Characters for bytes 0x80..=0x9F in Windows-1252; the five undefined
+/// bytes map to the matching C1 control, as in the WHATWG encoding standard
+const WINDOWS_1252_HIGH: [char; 32] = [
+    '\u{20AC}', '\u{0081}', '\u{201A}', '\u{0192}', '\u{201E}', '\u{2026}', '\u{2020}', '\u{2021}',
+    '\u{02C6}', '\u{2030}', '\u{0160}', '\u{2039}', '\u{0152}', '\u{008D}', '\u{017D}', '\u{008F}',
+    '\u{0090}', '\u{2018}', '\u{2019}', '\u{201C}', '\u{201D}', '\u{2022}', '\u{2013}', '\u{2014}',
+    '\u{02DC}', '\u{2122}', '\u{0161}', '\u{203A}', '\u{0153}', '\u{009D}', '\u{017E}', '\u{0178}',
+];
+
+/// Decodes an entry name, falling back to `encoding` if it is not valid UTF-8.
+///
+/// Every fallback keeps ASCII bytes as they are, so `/` and `.` stay where
+/// they were in the raw name.
+pub fn decode_name(bytes: &[u8], encoding: NameEncoding) -> Result<Cow<'_, str>, Utf8Error> {
+    match std::str::from_utf8(bytes) {
+        Ok(name) => Ok(Cow::Borrowed(name)),
+        Err(e) => match encoding {
+            NameEncoding::Utf8 => Err(e),
+            NameEncoding::Windows1252 => Ok(Cow::Owned(
+                bytes
+                    .iter()
+                    .map(|&b| match b {
+                        0x80..=0x9f => WINDOWS_1252_HIGH[(b - 0x80) as usize],
+                        _ => char::from(b),
+                    })
+                    .collect(),
+            )),
+            NameEncoding::Latin1 => Ok(Cow::Owned(bytes.iter().map(|&b| char::from(b)).collect())),
+            NameEncoding::Lossy => Ok(String::from_utf8_lossy(bytes)),
+        },
+    }
+}
+
+/// Formats a raw entry name for display, replacing invalid UTF-8 with U+FFFD
+pub fn display_name(bytes: &[u8]) -> Cow<'_, str> {
+    String::from_utf8_lossy(bytes)
+}
+
+/// Formats bytes such as an MD5 digest as lowercase hex
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// Gets the raw bytes of a path relative to a packed directory, using `/`
+/// as the separator.
+///
+/// On Unix these are the exact bytes of the file name. Elsewhere paths are
+/// converted lossily, since they are not byte strings.
+pub fn path_to_entry_bytes(path: &Path) -> Vec<u8> {
+    #[cfg(unix)]
+    let bytes = std::os::unix::ffi::OsStrExt::as_bytes(path.as_os_str()).to_vec();
+    #[cfg(not(unix))]
+    let bytes = path.to_string_lossy().into_owned().into_bytes();
+
+    bytes
+        .into_iter()
+        .map(|b| if b == b'\\' { b'/' } else { b })
+        .collect()
+}
+
 /// Normalizes a path for VPK storage (uses forward slashes)
 pub fn normalize_path(path: &str) -> String {
     path.replace('\\', "/")
 }
 
+/// Checks that a normalized archive path has no empty, `.` or `..` segments,
+/// returning the reason if it does
+pub(crate) fn check_archive_path(path: &str) -> std::result::Result<(), &'static str> {
+    for segment in path.split('/') {
+        match segment {
+            "" => return Err("contains an empty component"),
+            "." => return Err("contains a current directory component"),
+            ".." => return Err("contains a parent directory component"),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Normalizes a path for case- and separator-insensitive lookups.
+///
+/// Backslashes become forward slashes, ASCII letters are lowercased, and
+/// empty or `.` segments are dropped, so `.\Materials//Foo.VMT` becomes
+/// `materials/foo.vmt`.
+pub fn normalize_lookup_path(path: &str) -> String {
+    path.replace('\\', "/")
+        .split('/')
+        .filter(|segment| !segment.is_empty() && *segment != ".")
+        .collect::<Vec<_>>()
+        .join("/")
+        .to_ascii_lowercase()
+}
+
 /// Splits a filename into name and extension parts
 pub fn split_filename(filename: &str) -> Result<(String, String)> {
     if let Some(dot_pos) = filename.rfind('.') {
@@ -74,10 +197,36 @@ pub fn split_filename(filename: &str) -> Result<(String, String)> {
         let ext = filename[dot_pos + 1..].to_string();
         Ok((name, ext))
     } else {
-        anyhow::bail!("Files without an extension are not supported: {}", filename);
+        Err(VpkError::MissingExtension(filename.to_string()))
     }
 }
 
+/// Splits a raw entry path into directory, name and extension.
+///
+/// Files in the root directory get `" "` as their directory, as in the tree.
+/// Returns `None` if the file name has no extension.
+pub fn split_raw_path(path: &[u8]) -> Option<(&[u8], &[u8], &[u8])> {
+    let (dir, file_name): (&[u8], &[u8]) = match path.iter().rposition(|&b| b == b'/') {
+        Some(slash_pos) => (&path[..slash_pos], &path[slash_pos + 1..]),
+        None => (b" ", path),
+    };
+    let dot_pos = file_name.iter().rposition(|&b| b == b'.')?;
+    Some((dir, &file_name[..dot_pos], &file_name[dot_pos + 1..]))
+}
+
+/// Joins the raw directory, name and extension of a tree entry into a path
+pub fn join_raw_path(dir: &[u8], name: &[u8], ext: &[u8]) -> Vec<u8> {
+    let mut path = Vec::with_capacity(dir.len() + name.len() + ext.len() + 2);
+    if dir != b" " {
+        path.extend_from_slice(dir);
+        path.push(b'/');
+    }
+    path.extend_from_slice(name);
+    path.push(b'.');
+    path.extend_from_slice(ext);
+    path
+}
+
 /// Joins filename parts back together
 pub fn join_filename(name: &str, ext: &str) -> String {
     if ext.is_empty() {
@@ -92,7 +241,7 @@ pub fn read_exact_vec<R: Read>(reader: &mut R, count: usize) -> Result<Vec<u8>>
     let mut buffer = vec![0u8; count];
     reader
         .read_exact(&mut buffer)
-        .context("Failed to read exact bytes")?;
+        .io_context(|| format!("Failed to read {count} bytes"))?;
     Ok(buffer)
 }
 
@@ -133,5 +282,61 @@ mod tests {
     fn test_path_normalization() {
         assert_eq!(normalize_path("path\\to\\file"), "path/to/file");
         assert_eq!(normalize_path("path/to/file"), "path/to/file");
+
+        assert!(check_archive_path("materials/foo.vmt").is_ok());
+        assert!(check_archive_path("a/./b.txt").is_err());
+        assert!(check_archive_path("a//b.txt").is_err());
+        assert!(check_archive_path("a/../b.txt").is_err());
+    }
+
+    #[test]
+    fn test_raw_path_split() {
+        let parts = split_raw_path(b"materials/models/foo.vmt").unwrap();
+        assert_eq!(parts, (&b"materials/models"[..], &b"foo"[..], &b"vmt"[..]));
+        assert_eq!(
+            join_raw_path(parts.0, parts.1, parts.2),
+            b"materials/models/foo.vmt"
+        );
+
+        let parts = split_raw_path(b"readme.txt").unwrap();
+        assert_eq!(parts, (&b" "[..], &b"readme"[..], &b"txt"[..]));
+        assert_eq!(join_raw_path(parts.0, parts.1, parts.2), b"readme.txt");
+
+        assert!(split_raw_path(b"dir.d/no_extension").is_none());
+    }
+
+    #[test]
+    fn test_decode_name() {
+        let raw = b"caf\xe9_\x80.txt";
+        assert!(decode_name(raw, NameEncoding::Utf8).is_err());
+        assert_eq!(
+            decode_name(raw, NameEncoding::Windows1252).unwrap(),
+            "café_€.txt"
+        );
+        assert_eq!(
+            decode_name(raw, NameEncoding::Latin1).unwrap(),
+            "café_\u{80}.txt"
+        );
+        assert_eq!(
+            decode_name(raw, NameEncoding::Lossy).unwrap(),
+            "caf\u{FFFD}_\u{FFFD}.txt"
+        );
+        assert_eq!(
+            decode_name("café.txt".as_bytes(), NameEncoding::Utf8).unwrap(),
+            "café.txt"
+        );
+    }
+
+    #[test]
+    fn test_lookup_path_normalization() {
+        assert_eq!(
+            normalize_lookup_path("Materials\\Foo.VMT"),
+            "materials/foo.vmt"
+        );
+        assert_eq!(
+            normalize_lookup_path("./materials//models/./foo.vmt"),
+            "materials/models/foo.vmt"
+        );
+        assert_eq!(normalize_lookup_path("/readme.txt"), "readme.txt");
     }
 }
@@ -0,0 +1,293 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crc32fast::Hasher;
+use serde::{Serialize, Serializer};
+
+use crate::archive::ArchiveRange;
+use crate::entry::Entry;
+use crate::error::{Result, VpkError};
+use crate::parallel::{read_order, run_batches, split_batches, thread_count};
+use crate::progress::{Operation, Progress, ProgressTracker, SharedProgress};
+use crate::vpk::VPK;
+
+/// Options controlling how entry checksums are verified
+#[derive(Debug, Clone, Default)]
+pub struct VerifyOptions {
+    jobs: usize,
+    progress: SharedProgress,
+}
+
+impl VerifyOptions {
+    /// Creates the default options, which use one thread per available CPU
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the number of threads used by [`VPK::verify_files`]; `0`, the
+    /// default, uses one per available CPU
+    pub fn jobs(mut self, jobs: usize) -> Self {
+        self.jobs = jobs;
+        self
+    }
+
+    /// Reports each checked entry to `progress`, which can also cancel the
+    /// check
+    pub fn progress(mut self, progress: Arc<dyn Progress>) -> Self {
+        self.progress = SharedProgress(Some(progress));
+        self
+    }
+}
+
+/// An entry whose data does not match its stored CRC32
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CrcMismatch {
+    pub path: String,
+    pub expected: u32,
+    pub actual: u32,
+}
+
+/// An entry whose data could not be read
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct UnreadableEntry {
+    pub path: String,
+    pub error: String,
+}
+
+/// An archive chunk file that does not exist
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MissingChunk {
+    pub index: u16,
+    pub path: PathBuf,
+    /// Number of checked entries with data in the chunk
+    pub entries: usize,
+}
+
+/// Result of checking entry data against the CRC32s in the directory tree.
+///
+/// Serializes to JSON with `elapsed` given as `elapsed_seconds`.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct VerifyReport {
+    /// Number of entries checked
+    pub checked: usize,
+    /// Number of entries whose CRC32 matched
+    pub passed: usize,
+    /// Entries whose CRC32 did not match, sorted by path
+    pub mismatches: Vec<CrcMismatch>,
+    /// Entries that failed to read, sorted by path
+    pub unreadable: Vec<UnreadableEntry>,
+    /// Chunk files that are missing, sorted by index
+    pub missing_chunks: Vec<MissingChunk>,
+    #[serde(rename = "elapsed_seconds", serialize_with = "serialize_seconds")]
+    pub elapsed: Duration,
+}
+
+impl VerifyReport {
+    /// Returns true if every checked entry passed
+    pub fn is_ok(&self) -> bool {
+        self.passed == self.checked
+    }
+
+    /// Number of entries that did not pass, including those in missing chunks
+    pub fn failed(&self) -> usize {
+        self.checked - self.passed
+    }
+}
+
+fn serialize_seconds<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_f64(duration.as_secs_f64())
+}
+
+impl VPK {
+    /// Checks the CRC32 of every entry in parallel.
+    ///
+    /// See [`VPK::verify_entries`].
+    pub fn verify_files(&self, options: &VerifyOptions) -> Result<VerifyReport> {
+        self.index()?;
+        let entries: Vec<_> = self.entries().collect();
+        self.verify_entries(&entries, options)
+    }
+
+    /// Checks the CRC32 of the given entries in parallel.
+    ///
+    /// Entries are read in archive chunk and offset order, split into batches
+    /// over [`VerifyOptions::jobs`] threads like [`VPK::extract_entries`].
+    /// Failures are collected in the report instead of stopping the check, so
+    /// this only fails if the VPK has not been saved or the check is
+    /// cancelled by [`VerifyOptions::progress`].
+    pub fn verify_entries(
+        &self,
+        entries: &[Entry<'_>],
+        options: &VerifyOptions,
+    ) -> Result<VerifyReport> {
+        if self.path().is_none() {
+            return Err(VpkError::NotSaved("verify"));
+        }
+        let started = Instant::now();
+
+        let mut entries = entries.to_vec();
+        entries.sort_by_key(read_order);
+
+        let progress = ProgressTracker::new(
+            options.progress.0.as_deref(),
+            Operation::Verify,
+            entries.len(),
+            entries.iter().map(Entry::size).sum(),
+        );
+        let threads = thread_count(options.jobs, entries.len());
+        let batches = split_batches(&entries, threads, |entry| entry.metadata().archive_index);
+        let results = run_batches(&batches, threads, |batch| {
+            verify_batch(self, batch, &progress)
+        })?;
+
+        let mut report = VerifyReport {
+            checked: entries.len(),
+            ..VerifyReport::default()
+        };
+        let mut missing_chunks = BTreeMap::new();
+        for result in results {
+            report.passed += result.passed;
+            report.mismatches.extend(result.mismatches);
+            report.unreadable.extend(result.unreadable);
+            if let Some(chunk) = result.missing_chunk {
+                missing_chunks
+                    .entry(chunk.index)
+                    .and_modify(|missing: &mut MissingChunk| missing.entries += chunk.entries)
+                    .or_insert(chunk);
+            }
+        }
+        report.mismatches.sort_by(|a, b| a.path.cmp(&b.path));
+        report.unreadable.sort_by(|a, b| a.path.cmp(&b.path));
+        report.missing_chunks = missing_chunks.into_values().collect();
+        report.elapsed = started.elapsed();
+
+        Ok(report)
+    }
+}
+
+/// Partial report for one batch of entries
+#[derive(Default)]
+struct BatchReport {
+    passed: usize,
+    mismatches: Vec<CrcMismatch>,
+    unreadable: Vec<UnreadableEntry>,
+    missing_chunk: Option<MissingChunk>,
+}
+
+/// State of the archive chunk a batch reads from
+enum Chunk {
+    Unopened,
+    Open(Arc<File>),
+    Missing,
+    Unreadable(String),
+}
+
+/// Verifies a batch of entries whose data is in the same archive chunk
+fn verify_batch(
+    vpk: &VPK,
+    batch: &[Entry<'_>],
+    progress: &ProgressTracker<'_>,
+) -> Result<BatchReport> {
+    let mut report = BatchReport::default();
+    let mut chunk = Chunk::Unopened;
+    let mut buffer = vec![0u8; 64 * 1024];
+
+    for entry in batch {
+        let metadata = entry.metadata();
+        let mut hasher = Hasher::new();
+        hasher.update(entry.preload());
+
+        // Entries without archive data are still checked when the chunk is missing
+        if metadata.file_length > 0 {
+            if let Chunk::Unopened = chunk {
+                chunk = match vpk.archive(metadata.archive_index) {
+                    Ok(file) => Chunk::Open(file),
+                    Err(VpkError::MissingArchive {
+                        index,
+                        path,
+                        source,
+                    }) if source.kind() == io::ErrorKind::NotFound => {
+                        report.missing_chunk = Some(MissingChunk {
+                            index,
+                            path,
+                            entries: 0,
+                        });
+                        Chunk::Missing
+                    }
+                    Err(e) => Chunk::Unreadable(e.to_string()),
+                };
+            }
+
+            let archive = match &chunk {
+                Chunk::Open(archive) => archive,
+                Chunk::Missing => {
+                    if let Some(missing) = &mut report.missing_chunk {
+                        missing.entries += 1;
+                    }
+                    progress.file_done(entry.path(), entry.size())?;
+                    continue;
+                }
+                Chunk::Unreadable(error) => {
+                    report.unreadable.push(UnreadableEntry {
+                        path: entry.path().to_string(),
+                        error: error.clone(),
+                    });
+                    progress.file_done(entry.path(), entry.size())?;
+                    continue;
+                }
+                Chunk::Unopened => unreachable!("the chunk was opened above"),
+            };
+
+            if let Err(e) = hash_archive_data(archive, entry, &mut hasher, &mut buffer) {
+                report.unreadable.push(UnreadableEntry {
+                    path: entry.path().to_string(),
+                    error: e.to_string(),
+                });
+                progress.file_done(entry.path(), entry.size())?;
+                continue;
+            }
+        }
+
+        let actual = hasher.finalize();
+        if actual == metadata.crc32 {
+            report.passed += 1;
+        } else {
+            report.mismatches.push(CrcMismatch {
+                path: entry.path().to_string(),
+                expected: metadata.crc32,
+                actual,
+            });
+        }
+        progress.file_done(entry.path(), entry.size())?;
+    }
+
+    Ok(report)
+}
+
+/// Feeds the part of an entry stored in `archive` into `hasher`
+fn hash_archive_data(
+    archive: &File,
+    entry: &Entry<'_>,
+    hasher: &mut Hasher,
+    buffer: &mut [u8],
+) -> io::Result<()> {
+    let metadata = entry.metadata();
+    let mut data = ArchiveRange::new(
+        archive,
+        metadata.archive_offset as u64,
+        metadata.file_length as u64,
+    );
+
+    let mut remaining = metadata.file_length as usize;
+    while remaining > 0 {
+        let to_read = remaining.min(buffer.len());
+        data.read_exact(&mut buffer[..to_read])?;
+        hasher.update(&buffer[..to_read]);
+        remaining -= to_read;
+    }
+    Ok(())
+}
@@ -1,15 +1,44 @@
-use anyhow::{Context, Result, bail};
-use crc32fast::Hasher;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, btree_map};
 use std::fs::File;
-use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
 use std::path::{Path, PathBuf};
-use walkdir::WalkDir;
+use std::sync::{Arc, OnceLock};
 
+use serde::{Serialize, Serializer};
+
+use crate::archive::{ArchiveHandles, ArchiveRange, copy_range};
+use crate::browse::DirIndex;
+use crate::error::{IoResultExt, Result, VpkError};
 use crate::file::{FileMetadata, VPKFile};
+use crate::pack::PackOptions;
+use crate::progress::{Operation, Progress, ProgressTracker};
 use crate::utils::*;
 
-type FileHashMap<'a> = HashMap<String, HashMap<String, Vec<(String, &'a FileMetadata)>>>;
+/// Entries grouped by extension and directory, as `(name, full path, metadata)`
+type FileHashMap<'a> =
+    HashMap<&'a [u8], HashMap<&'a [u8], Vec<(&'a [u8], &'a str, &'a FileMetadata)>>>;
+
+/// Where the data an entry stores outside the tree is read from
+#[derive(Clone, Copy)]
+enum EntryData<'a> {
+    /// Held in memory by an entry that has not been saved yet
+    Memory(&'a [u8]),
+    /// Stored in an archive file of the VPK this one was opened from
+    Archive {
+        index: u16,
+        offset: u64,
+        length: u64,
+    },
+}
+
+impl EntryData<'_> {
+    fn len(&self) -> u64 {
+        match self {
+            EntryData::Memory(bytes) => bytes.len() as u64,
+            EntryData::Archive { length, .. } => *length,
+        }
+    }
+}
 
 /// VPK file format versions
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -18,8 +47,15 @@ pub enum VPKVersion {
     V2 = 2,
 }
 
+/// Serializes as the version number
+impl Serialize for VPKVersion {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_u32(*self as u32)
+    }
+}
+
 /// VPK header information
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct VPKHeader {
     pub signature: u32,
     pub version: VPKVersion,
@@ -33,91 +69,478 @@ pub struct VPKHeader {
 }
 
 /// VPK checksums (V2 only)
-#[derive(Debug, Clone)]
+///
+/// Serializes the MD5s as lowercase hex strings.
+#[derive(Debug, Clone, Serialize)]
 pub struct VPKChecksums {
+    #[serde(serialize_with = "serialize_md5")]
     pub tree_checksum: [u8; 16],
+    #[serde(serialize_with = "serialize_md5")]
     pub chunk_hashes_checksum: [u8; 16],
+    #[serde(serialize_with = "serialize_md5")]
     pub file_checksum: [u8; 16],
 }
 
+fn serialize_md5<S: Serializer>(
+    md5: &[u8; 16],
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error> {
+    serializer.serialize_str(&to_hex(md5))
+}
+
+/// A contiguous region of the directory file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct VPKSection {
+    pub offset: u64,
+    pub length: u64,
+}
+
+impl VPKSection {
+    /// Offset one past the last byte of the section
+    pub fn end(&self) -> u64 {
+        self.offset + self.length
+    }
+}
+
+/// Layout of the sections that follow the header in the directory file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct VPKSections {
+    pub tree: VPKSection,
+    pub embedded_data: VPKSection,
+    pub chunk_hashes: VPKSection,
+    pub self_hashes: VPKSection,
+    pub signature: VPKSection,
+}
+
+impl VPKHeader {
+    /// Computes the offset and length of every section described by the header.
+    ///
+    /// V1 files only describe the tree; the remaining sections are empty and
+    /// start right after it.
+    pub fn sections(&self) -> VPKSections {
+        let section = |offset: u64, length: Option<u32>| VPKSection {
+            offset,
+            length: length.unwrap_or(0) as u64,
+        };
+
+        let tree = section(self.header_length as u64, Some(self.tree_length));
+        let embedded_data = section(tree.end(), self.embed_chunk_length);
+        let chunk_hashes = section(embedded_data.end(), self.chunk_hashes_length);
+        let self_hashes = section(chunk_hashes.end(), self.self_hashes_length);
+        let signature = section(self_hashes.end(), self.signature_length);
+
+        VPKSections {
+            tree,
+            embedded_data,
+            chunk_hashes,
+            self_hashes,
+            signature,
+        }
+    }
+}
+
+/// Header, section layout and stored checksums of a VPK, read without parsing the file tree
+#[derive(Debug, Clone)]
+pub struct VPKHeaderInfo {
+    pub header: VPKHeader,
+    pub sections: VPKSections,
+    pub checksums: Option<VPKChecksums>,
+    /// Size of the directory file on disk
+    pub file_size: u64,
+}
+
+/// How paths passed to lookups such as [`VPK::get_file`] are matched against entries
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PathLookup {
+    /// Paths must match the stored entry byte for byte
+    #[default]
+    Exact,
+    /// Paths are compared the way the Source engine does: ASCII case,
+    /// backslashes, `./` segments and repeated slashes are ignored
+    Normalized,
+}
+
+/// Resource limits applied while parsing a VPK, so that corrupt or hostile
+/// files fail cleanly instead of exhausting memory
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    /// Largest accepted file tree, in bytes
+    pub max_tree_length: u32,
+    /// Largest accepted number of entries
+    pub max_entries: usize,
+    /// Longest accepted extension, directory or file name, in bytes
+    pub max_string_length: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            max_tree_length: 256 * 1024 * 1024,
+            max_entries: 4_000_000,
+            max_string_length: 4096,
+        }
+    }
+}
+
+/// Default number of archive chunk files a VPK keeps open at once
+pub const DEFAULT_MAX_OPEN_ARCHIVES: usize = 64;
+
+/// Most bytes reserved up front when reading a whole entry; the lengths in
+/// the tree are not checked against archives that could not be found
+const MAX_READ_RESERVATION: u64 = 1 << 20;
+
+/// Options controlling how a VPK is opened
+#[derive(Debug, Clone)]
+pub struct OpenOptions {
+    lazy: bool,
+    path_lookup: PathLookup,
+    limits: Limits,
+    name_encoding: NameEncoding,
+    max_open_archives: usize,
+}
+
+impl Default for OpenOptions {
+    fn default() -> Self {
+        OpenOptions {
+            lazy: false,
+            path_lookup: PathLookup::default(),
+            limits: Limits::default(),
+            name_encoding: NameEncoding::default(),
+            max_open_archives: DEFAULT_MAX_OPEN_ARCHIVES,
+        }
+    }
+}
+
+impl OpenOptions {
+    /// Creates the default options: the file tree is read eagerly
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Defers reading the file tree until the first lookup
+    pub fn lazy(mut self, lazy: bool) -> Self {
+        self.lazy = lazy;
+        self
+    }
+
+    /// Sets how lookup paths are matched against entries
+    pub fn path_lookup(mut self, path_lookup: PathLookup) -> Self {
+        self.path_lookup = path_lookup;
+        self
+    }
+
+    /// Sets the resource limits applied while parsing
+    pub fn limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Sets how entry names that are not valid UTF-8 are decoded
+    pub fn name_encoding(mut self, name_encoding: NameEncoding) -> Self {
+        self.name_encoding = name_encoding;
+        self
+    }
+
+    /// Sets how many archive chunk files are kept open at once; opening
+    /// another one closes the least recently used. At least one is kept.
+    pub fn max_open_archives(mut self, max_open_archives: usize) -> Self {
+        self.max_open_archives = max_open_archives;
+        self
+    }
+
+    /// Opens the VPK at `path` with these options
+    pub fn open<P: AsRef<Path>>(&self, path: P) -> Result<VPK> {
+        let path = path.as_ref().to_path_buf();
+        let mut file = VPK::open_reader(&path)?;
+
+        let mut vpk = self.read_vpk(&mut file, !self.lazy)?;
+        if let Some(tree) = vpk.tree.get() {
+            VPK::validate_archive_bounds(&path, tree)?;
+        }
+        vpk.path = Some(path);
+        Ok(vpk)
+    }
+
+    /// Reads a VPK directory file from any seekable reader, such as an
+    /// in-memory buffer.
+    ///
+    /// The file tree is always read eagerly. The returned VPK has no path, so
+    /// entry contents cannot be read from it.
+    pub fn from_reader<R: Read + Seek>(&self, reader: &mut R) -> Result<VPK> {
+        self.read_vpk(reader, true)
+    }
+
+    /// Reads the header, checksums and, if `read_tree` is set, the file tree
+    fn read_vpk<R: Read + Seek>(&self, reader: &mut R, read_tree: bool) -> Result<VPK> {
+        let file_size = reader.seek(SeekFrom::End(0))?;
+        reader.seek(SeekFrom::Start(0))?;
+
+        let header = VPK::read_header(reader)?;
+        VPK::validate_header(&header, &self.limits, file_size)?;
+
+        let tree = OnceLock::new();
+        if read_tree {
+            let _ = tree.set(VPK::read_file_tree(
+                reader,
+                &header,
+                &self.limits,
+                self.name_encoding,
+                file_size,
+            )?);
+        }
+        let checksums = if header.version == VPKVersion::V2 {
+            Some(VPK::read_checksums(reader, &header)?)
+        } else {
+            None
+        };
+
+        Ok(VPK {
+            path: None,
+            header,
+            tree,
+            checksums,
+            path_lookup: self.path_lookup,
+            lookup_index: OnceLock::new(),
+            dir_index: OnceLock::new(),
+            archives: ArchiveHandles::new(self.max_open_archives),
+            limits: self.limits,
+            name_encoding: self.name_encoding,
+        })
+    }
+}
+
+/// Cursor over the in-memory file tree that reports errors at absolute file offsets
+struct TreeReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    /// Offset of the tree within the directory file
+    base: u64,
+    max_string_length: usize,
+}
+
+impl<'a> TreeReader<'a> {
+    /// Absolute file offset of the next byte
+    fn offset(&self) -> u64 {
+        self.base + self.pos as u64
+    }
+
+    /// Error for a read running past the end of the tree
+    fn truncated(&self) -> VpkError {
+        VpkError::TruncatedTree {
+            offset: self.base + self.data.len() as u64,
+        }
+    }
+
+    fn bytes(&mut self, count: usize) -> Result<&'a [u8]> {
+        let end = self
+            .pos
+            .checked_add(count)
+            .filter(|&end| end <= self.data.len())
+            .ok_or_else(|| self.truncated())?;
+        let bytes = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        let bytes = self.bytes(2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        let bytes = self.bytes(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    /// Reads a null-terminated string and decodes it with `encoding`,
+    /// returning both the decoded and the raw form
+    fn name(&mut self, encoding: NameEncoding) -> Result<(String, &'a [u8])> {
+        let offset = self.offset();
+        let raw = self.cstring()?;
+        let name = decode_name(raw, encoding).map_err(|source| VpkError::InvalidUtf8 {
+            what: format!("entry name at offset {offset}"),
+            source,
+        })?;
+        Ok((name.into_owned(), raw))
+    }
+
+    /// Reads the raw bytes of a null-terminated string
+    fn cstring(&mut self) -> Result<&'a [u8]> {
+        let offset = self.offset();
+        let length = self.data[self.pos..]
+            .iter()
+            .position(|&b| b == 0)
+            .ok_or_else(|| self.truncated())?;
+        if length > self.max_string_length {
+            return Err(VpkError::LimitExceeded {
+                what: format!("String at offset {offset}"),
+                value: length as u64,
+                limit: self.max_string_length as u64,
+            });
+        }
+        let bytes = self.bytes(length + 1)?;
+        Ok(&bytes[..length])
+    }
+}
+
 /// Main VPK structure that handles both reading and writing
 pub struct VPK {
     path: Option<PathBuf>,
     header: VPKHeader,
-    tree: HashMap<String, FileMetadata>,
+    tree: OnceLock<HashMap<String, FileMetadata>>,
     checksums: Option<VPKChecksums>,
+    path_lookup: PathLookup,
+    /// Normalized path -> entry paths, built on the first normalized lookup
+    lookup_index: OnceLock<HashMap<String, Vec<String>>>,
+    /// Directory structure, built on the first browsing call
+    dir_index: OnceLock<DirIndex>,
+    /// Limits for reading the tree of a lazily opened VPK
+    limits: Limits,
+    name_encoding: NameEncoding,
+    /// Archive files opened by readers, shared between them
+    archives: ArchiveHandles,
 }
 
 impl VPK {
     /// Opens an existing VPK file for reading
     pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
-        let path = path.as_ref().to_path_buf();
-        let mut file = BufReader::new(
-            File::open(&path)
-                .with_context(|| format!("Failed to open VPK file: {}", path.display()))?,
-        );
+        OpenOptions::new().open(path)
+    }
+
+    /// Opens a VPK whose file tree is only read on the first lookup
+    pub fn open_lazy<P: AsRef<Path>>(path: P) -> Result<Self> {
+        OpenOptions::new().lazy(true).open(path)
+    }
+
+    /// Reads only the header, section layout and checksums of a VPK.
+    ///
+    /// This is much cheaper than [`VPK::open`] for large archives since the
+    /// file tree is never parsed.
+    pub fn open_header<P: AsRef<Path>>(path: P) -> Result<VPKHeaderInfo> {
+        let path = path.as_ref();
+        let mut file = Self::open_reader(path)?;
+        let file_size = file
+            .get_ref()
+            .metadata()
+            .io_context(|| format!("Failed to stat VPK file: {}", path.display()))?
+            .len();
 
         let header = Self::read_header(&mut file)?;
-        let tree = Self::read_file_tree(&mut file, &header)?;
+        // The tree is never parsed here, so only its bounds are checked
+        let limits = Limits {
+            max_tree_length: u32::MAX,
+            ..Limits::default()
+        };
+        Self::validate_header(&header, &limits, file_size)?;
         let checksums = if header.version == VPKVersion::V2 {
             Some(Self::read_checksums(&mut file, &header)?)
         } else {
             None
         };
 
-        Ok(VPK {
-            path: Some(path),
+        Ok(VPKHeaderInfo {
+            sections: header.sections(),
             header,
-            tree,
             checksums,
+            file_size,
         })
     }
 
-    /// Creates a new VPK from a directory structure
-    pub fn from_directory<P: AsRef<Path>>(path: P) -> Result<Self> {
-        let path = path.as_ref();
-        if !path.is_dir() {
-            bail!("Path is not a directory: {}", path.display());
+    /// Reads a VPK directory file from a seekable reader with default options
+    pub fn from_reader<R: Read + Seek>(reader: &mut R) -> Result<Self> {
+        OpenOptions::new().from_reader(reader)
+    }
+
+    /// Opens the directory file for buffered reading
+    fn open_reader(path: &Path) -> Result<BufReader<File>> {
+        let file = File::open(path)
+            .io_context(|| format!("Failed to open VPK file: {}", path.display()))?;
+        Ok(BufReader::new(file))
+    }
+
+    /// Returns the file tree, reading it from disk first if the VPK was opened lazily
+    pub(crate) fn tree(&self) -> Result<&HashMap<String, FileMetadata>> {
+        if let Some(tree) = self.tree.get() {
+            return Ok(tree);
         }
 
-        let mut tree = HashMap::new();
-        // let mut file_count = 0;
-
-        // Walk the directory and build the file tree
-        for entry in WalkDir::new(path).into_iter().filter_map(|e| e.ok()) {
-            if entry.file_type().is_file() {
-                let relative_path = entry
-                    .path()
-                    .strip_prefix(path)
-                    .context("Failed to get relative path")?;
-
-                let path_str = normalize_path(&relative_path.to_string_lossy());
-                let (_name, _ext) = split_filename(&entry.file_name().to_string_lossy())?;
-
-                // Read file data for preload and calculate CRC
-                let file_data = std::fs::read(entry.path())
-                    .with_context(|| format!("Failed to read file: {}", entry.path().display()))?;
-
-                let mut hasher = Hasher::new();
-                hasher.update(&file_data);
-                let crc32 = hasher.finalize();
-
-                // For now, we embed all files (no separate archive files)
-                let metadata = FileMetadata {
-                    preload: file_data,
-                    crc32,
-                    preload_length: 0, // Will be set during save
-                    archive_index: EMBEDDED_ARCHIVE_INDEX,
-                    archive_offset: 0, // Will be set during save
-                    file_length: 0,    // Will be set during save
-                };
+        let path = self.path.as_ref().ok_or(VpkError::NotSaved("index"))?;
+        let mut file = Self::open_reader(path)?;
+        let file_size = file.seek(SeekFrom::End(0))?;
+        file.seek(SeekFrom::Start(self.header.header_length as u64))?;
+        let tree = Self::read_file_tree(
+            &mut file,
+            &self.header,
+            &self.limits,
+            self.name_encoding,
+            file_size,
+        )?;
+        Self::validate_archive_bounds(path, &tree)?;
+
+        // Another thread may have won the race; either tree is identical
+        let _ = self.tree.set(tree);
+        Ok(self.tree.get().expect("tree was just initialized"))
+    }
 
-                tree.insert(path_str, metadata);
-                // file_count += 1;
-            }
+    /// Returns the normalized lookup index, building it on first use
+    fn lookup_index(&self) -> Result<&HashMap<String, Vec<String>>> {
+        if let Some(index) = self.lookup_index.get() {
+            return Ok(index);
         }
 
-        let tree_length = Self::calculate_tree_length(&tree);
+        let mut index: HashMap<String, Vec<String>> = HashMap::new();
+        for path in self.tree()?.keys() {
+            index
+                .entry(normalize_lookup_path(path))
+                .or_default()
+                .push(path.clone());
+        }
+        for paths in index.values_mut() {
+            paths.sort();
+        }
+
+        let _ = self.lookup_index.set(index);
+        Ok(self
+            .lookup_index
+            .get()
+            .expect("lookup index was just initialized"))
+    }
+
+    /// Returns the directory index, building it on first use
+    pub(crate) fn dir_index(&self) -> Result<&DirIndex> {
+        if let Some(index) = self.dir_index.get() {
+            return Ok(index);
+        }
+
+        let _ = self.dir_index.set(DirIndex::build(self.tree()?));
+        Ok(self
+            .dir_index
+            .get()
+            .expect("directory index was just initialized"))
+    }
+
+    /// Returns true once the file tree has been read
+    pub fn is_indexed(&self) -> bool {
+        self.tree.get().is_some()
+    }
+
+    /// Reads the file tree of a lazily opened VPK if it has not been read yet.
+    ///
+    /// Accessors such as [`VPK::file_count`], [`VPK::contains`] and
+    /// [`VPK::entries`] treat a tree that fails to parse as empty; call this
+    /// first to get the error instead.
+    pub fn index(&self) -> Result<()> {
+        self.tree().map(|_| ())
+    }
+
+    /// Creates a new VPK from a directory structure
+    pub fn from_directory<P: AsRef<Path>>(path: P) -> Result<Self> {
+        PackOptions::new().pack_directory(path)
+    }
+
+    /// Creates an unsaved VPK holding the given entries
+    pub(crate) fn from_tree(tree: HashMap<String, FileMetadata>) -> Result<Self> {
+        let tree_length = Self::calculate_tree_length(&Self::group_entries(&tree)?);
 
         let header = VPKHeader {
             signature: VPK_SIGNATURE,
@@ -133,25 +556,111 @@ impl VPK {
         Ok(VPK {
             path: None,
             header,
-            tree,
+            tree: OnceLock::from(tree),
             checksums: None,
+            path_lookup: PathLookup::Exact,
+            lookup_index: OnceLock::new(),
+            dir_index: OnceLock::new(),
+            archives: ArchiveHandles::new(DEFAULT_MAX_OPEN_ARCHIVES),
+            limits: Limits::default(),
+            name_encoding: NameEncoding::default(),
         })
     }
 
     /// Saves the VPK to the specified path
     pub fn save<P: AsRef<Path>>(&self, output_path: P) -> Result<()> {
-        let output_path = output_path.as_ref();
-        let mut file =
-            BufWriter::new(File::create(output_path).with_context(|| {
-                format!("Failed to create VPK file: {}", output_path.display())
-            })?);
+        self.write_to(output_path.as_ref(), None)
+    }
+
+    /// Saves the VPK like [`VPK::save`], reporting each written entry to
+    /// `progress`, which can also cancel the save.
+    ///
+    /// A cancelled or failed save removes the files it had written.
+    pub fn save_with_progress<P: AsRef<Path>>(
+        &self,
+        output_path: P,
+        progress: &dyn Progress,
+    ) -> Result<()> {
+        self.write_to(output_path.as_ref(), Some(progress))
+    }
+
+    fn write_to(&self, output_path: &Path, progress: Option<&dyn Progress>) -> Result<()> {
+        let uses_chunks = self
+            .tree()?
+            .values()
+            .any(|metadata| metadata.archive_index != EMBEDDED_ARCHIVE_INDEX);
+        let is_dir_file = output_path
+            .file_name()
+            .is_some_and(|name| name.to_string_lossy().ends_with("_dir.vpk"));
+        if uses_chunks && !is_dir_file {
+            return Err(VpkError::Unsupported(
+                "VPKs with archive chunks must be saved to a path ending in _dir.vpk",
+            ));
+        }
+
+        // Entries of an opened VPK are copied from its files, so they cannot
+        // be overwritten
+        if let Some(source) = &self.path
+            && let (Ok(source), Ok(output)) = (source.canonicalize(), output_path.canonicalize())
+            && source == output
+        {
+            return Err(VpkError::Unsupported(
+                "Cannot save a VPK over the file it was opened from",
+            ));
+        }
+
+        let file = File::create(output_path)
+            .io_context(|| format!("Failed to create VPK file: {}", output_path.display()))?;
+
+        let tree = self.tree()?;
+        let progress = ProgressTracker::new(
+            progress,
+            Operation::Save,
+            tree.len(),
+            tree.values().map(FileMetadata::total_length).sum(),
+        );
+        let mut chunk_paths = Vec::new();
+        let result = self.write_vpk(
+            BufWriter::new(file),
+            output_path,
+            &progress,
+            &mut chunk_paths,
+        );
+
+        // Leave no partial output behind; removal errors would hide the cause
+        if result.is_err() {
+            let _ = std::fs::remove_file(output_path);
+            for path in &chunk_paths {
+                let _ = std::fs::remove_file(path);
+            }
+        }
+        result.map_err(|e| {
+            e.or_io_context(|| format!("Failed to write VPK file: {}", output_path.display()))
+        })
+    }
+
+    /// Writes the whole VPK to a freshly created file
+    fn write_vpk(
+        &self,
+        mut file: BufWriter<File>,
+        output_path: &Path,
+        progress: &ProgressTracker<'_>,
+        chunk_paths: &mut Vec<PathBuf>,
+    ) -> Result<()> {
+        let grouped_files = Self::group_entries(self.tree()?)?;
 
         // Write header (will update embed_chunk_length later)
-        self.write_header(&mut file)?;
+        self.write_header(&mut file, Self::calculate_tree_length(&grouped_files))?;
         let header_end = file.stream_position()? as u32;
 
         // Write file tree and embedded data
-        let embed_chunk_length = self.write_file_tree_and_data(&mut file)?;
+        let embed_chunk_length = self.write_file_tree_and_data(
+            &mut file,
+            &grouped_files,
+            output_path,
+            progress,
+            chunk_paths,
+        )?;
 
         // Calculate and write checksums for V2
         if self.header.version == VPKVersion::V2 {
@@ -162,9 +671,7 @@ impl VPK {
 
             // Flush buffer and get underlying file for checksum calculation
             file.flush()?;
-            let mut underlying_file = file
-                .into_inner()
-                .map_err(|e| anyhow::anyhow!("Failed to get underlying file: {}", e))?;
+            let mut underlying_file = file.into_inner().map_err(|e| e.into_error())?;
 
             self.write_checksums(&mut underlying_file, header_end, embed_chunk_length)?;
         } else {
@@ -179,7 +686,7 @@ impl VPK {
         let mut header_bytes = [0u8; 12];
         reader
             .read_exact(&mut header_bytes)
-            .context("Failed to read VPK header")?;
+            .io_context(|| "Failed to read VPK header")?;
 
         let signature = u32::from_le_bytes([
             header_bytes[0],
@@ -201,13 +708,13 @@ impl VPK {
         ]);
 
         if signature != VPK_SIGNATURE {
-            bail!("Invalid VPK signature: 0x{:08x}", signature);
+            return Err(VpkError::InvalidSignature { found: signature });
         }
 
         let version = match version_num {
             1 => VPKVersion::V1,
             2 => VPKVersion::V2,
-            _ => bail!("Unsupported VPK version: {}", version_num),
+            _ => return Err(VpkError::UnsupportedVersion(version_num)),
         };
 
         let mut header = VPKHeader {
@@ -226,7 +733,7 @@ impl VPK {
             let mut v2_header = [0u8; 16];
             reader
                 .read_exact(&mut v2_header)
-                .context("Failed to read V2 header")?;
+                .io_context(|| "Failed to read V2 header")?;
 
             header.embed_chunk_length = Some(u32::from_le_bytes([
                 v2_header[0],
@@ -262,17 +769,50 @@ impl VPK {
     fn read_file_tree<R: Read>(
         reader: &mut R,
         header: &VPKHeader,
+        limits: &Limits,
+        name_encoding: NameEncoding,
+        file_size: u64,
     ) -> Result<HashMap<String, FileMetadata>> {
+        // Embedded data must lie within the embedded data section (V2) or the
+        // rest of the file (V1)
+        let embedded_end = match header.version {
+            VPKVersion::V1 => file_size,
+            VPKVersion::V2 => header.sections().embedded_data.end(),
+        };
+
+        let mut data = Vec::new();
+        reader
+            .take(header.tree_length as u64)
+            .read_to_end(&mut data)
+            .io_context(|| {
+                format!(
+                    "Failed to read file tree at offset {}",
+                    header.header_length
+                )
+            })?;
+        if data.len() < header.tree_length as usize {
+            return Err(VpkError::TruncatedTree {
+                offset: header.header_length as u64 + data.len() as u64,
+            });
+        }
+
+        let mut reader = TreeReader {
+            data: &data,
+            pos: 0,
+            base: header.header_length as u64,
+            max_string_length: limits.max_string_length,
+        };
+        let mut entry_count = 0;
         let mut tree = HashMap::new();
 
         loop {
-            let ext = read_cstring(reader)?;
+            let (ext, raw_ext) = reader.name(name_encoding)?;
             if ext.is_empty() {
                 break;
             }
 
             loop {
-                let path = read_cstring(reader)?;
+                let (path, raw_dir) = reader.name(name_encoding)?;
                 if path.is_empty() {
                     break;
                 }
@@ -284,56 +824,63 @@ impl VPK {
                 };
 
                 loop {
-                    let name = read_cstring(reader)?;
+                    let (name, raw_name) = reader.name(name_encoding)?;
                     if name.is_empty() {
                         break;
                     }
 
-                    // Read file metadata
-                    let mut metadata_bytes = [0u8; 18];
-                    reader
-                        .read_exact(&mut metadata_bytes)
-                        .context("Failed to read file metadata")?;
-
-                    let crc32 = u32::from_le_bytes([
-                        metadata_bytes[0],
-                        metadata_bytes[1],
-                        metadata_bytes[2],
-                        metadata_bytes[3],
-                    ]);
-                    let preload_length = u16::from_le_bytes([metadata_bytes[4], metadata_bytes[5]]);
-                    let archive_index = u16::from_le_bytes([metadata_bytes[6], metadata_bytes[7]]);
-                    let archive_offset = u32::from_le_bytes([
-                        metadata_bytes[8],
-                        metadata_bytes[9],
-                        metadata_bytes[10],
-                        metadata_bytes[11],
-                    ]);
-                    let file_length = u32::from_le_bytes([
-                        metadata_bytes[12],
-                        metadata_bytes[13],
-                        metadata_bytes[14],
-                        metadata_bytes[15],
-                    ]);
-                    let suffix = u16::from_le_bytes([metadata_bytes[16], metadata_bytes[17]]);
+                    entry_count += 1;
+                    if entry_count > limits.max_entries {
+                        return Err(VpkError::LimitExceeded {
+                            what: "Entry count".to_string(),
+                            value: entry_count as u64,
+                            limit: limits.max_entries as u64,
+                        });
+                    }
+                    let full_path = format!("{normalized_path}{name}.{ext}");
+                    let raw_path = join_raw_path(raw_dir, raw_name, raw_ext);
+                    let raw_path = (raw_path != full_path.as_bytes()).then_some(raw_path);
 
+                    // Read file metadata
+                    let crc32 = reader.u32()?;
+                    let preload_length = reader.u16()?;
+                    let archive_index = reader.u16()?;
+                    let archive_offset = reader.u32()?;
+                    let file_length = reader.u32()?;
+
+                    let suffix_offset = reader.offset();
+                    let suffix = reader.u16()?;
                     if suffix != METADATA_SUFFIX {
-                        bail!("Invalid metadata suffix: 0x{:04x}", suffix);
+                        return Err(VpkError::InvalidMetadataSuffix {
+                            offset: suffix_offset,
+                            suffix,
+                        });
                     }
 
                     // Adjust archive offset for embedded files
                     let actual_archive_offset = if archive_index == EMBEDDED_ARCHIVE_INDEX {
-                        header.header_length + header.tree_length + archive_offset
+                        let offset = header.header_length as u64
+                            + header.tree_length as u64
+                            + archive_offset as u64;
+                        let end = offset + file_length as u64;
+                        if end > embedded_end {
+                            return Err(VpkError::OutOfBounds {
+                                what: format!("Embedded entry {full_path}"),
+                                end,
+                                size: embedded_end,
+                            });
+                        }
+                        u32::try_from(offset).map_err(|_| VpkError::OutOfBounds {
+                            what: format!("Embedded entry {full_path}"),
+                            end,
+                            size: u32::MAX as u64,
+                        })?
                     } else {
                         archive_offset
                     };
 
                     // Read preload data
-                    let preload = if preload_length > 0 {
-                        read_exact_vec(reader, preload_length as usize)?
-                    } else {
-                        Vec::new()
-                    };
+                    let preload = reader.bytes(preload_length as usize)?.to_vec();
 
                     let metadata = FileMetadata {
                         preload,
@@ -342,9 +889,16 @@ impl VPK {
                         archive_index,
                         archive_offset: actual_archive_offset,
                         file_length,
+                        raw_path,
                     };
 
-                    let full_path = format!("{normalized_path}{name}.{ext}");
+                    // Distinct raw names must not silently replace each other;
+                    // `raw_path` is only set when it differs from the path
+                    if tree.get(&full_path).is_some_and(|existing: &FileMetadata| {
+                        existing.raw_path != metadata.raw_path
+                    }) {
+                        return Err(VpkError::NameCollision { path: full_path });
+                    }
                     tree.insert(full_path, metadata);
                 }
             }
@@ -353,27 +907,87 @@ impl VPK {
         Ok(tree)
     }
 
+    /// Checks the header against the limits and the size of the directory file
+    fn validate_header(header: &VPKHeader, limits: &Limits, file_size: u64) -> Result<()> {
+        if header.tree_length > limits.max_tree_length {
+            return Err(VpkError::LimitExceeded {
+                what: "Tree length".to_string(),
+                value: header.tree_length as u64,
+                limit: limits.max_tree_length as u64,
+            });
+        }
+
+        let sections = header.sections();
+        if sections.tree.end() > file_size {
+            return Err(VpkError::TruncatedTree { offset: file_size });
+        }
+        if header.version == VPKVersion::V2 && sections.signature.end() > file_size {
+            return Err(VpkError::OutOfBounds {
+                what: "Sections declared by the V2 header".to_string(),
+                end: sections.signature.end(),
+                size: file_size,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Checks that entries stored in numbered archive chunks fit inside them.
+    ///
+    /// Chunks that cannot be found are skipped; reading from them reports
+    /// [`VpkError::MissingArchive`].
+    fn validate_archive_bounds(
+        vpk_path: &Path,
+        tree: &HashMap<String, FileMetadata>,
+    ) -> Result<()> {
+        let mut chunk_sizes: HashMap<u16, Option<u64>> = HashMap::new();
+
+        for (path, metadata) in tree {
+            if metadata.archive_index == EMBEDDED_ARCHIVE_INDEX || metadata.file_length == 0 {
+                continue;
+            }
+
+            let size = *chunk_sizes
+                .entry(metadata.archive_index)
+                .or_insert_with(|| {
+                    let chunk_path =
+                        VPKFile::resolve_archive_path(vpk_path, metadata.archive_index);
+                    std::fs::metadata(chunk_path).ok().map(|m| m.len())
+                });
+
+            let end = metadata.archive_offset as u64 + metadata.file_length as u64;
+            if let Some(size) = size
+                && end > size
+            {
+                return Err(VpkError::OutOfBounds {
+                    what: format!("Entry {path} in archive {}", metadata.archive_index),
+                    end,
+                    size,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
     /// Reads checksums from V2 VPK files
     fn read_checksums<R: Read + Seek>(reader: &mut R, header: &VPKHeader) -> Result<VPKChecksums> {
         if header.version != VPKVersion::V2 {
-            bail!("Checksums only available in VPK V2");
+            return Err(VpkError::Unsupported("Checksums only available in VPK V2"));
         }
 
-        let embed_chunk_length = header.embed_chunk_length.unwrap_or(0);
-        let chunk_hashes_length = header.chunk_hashes_length.unwrap_or(0);
-
-        // Seek to checksums section
-        let checksums_offset =
-            header.header_length + header.tree_length + embed_chunk_length + chunk_hashes_length;
-        reader.seek(SeekFrom::Start(checksums_offset as u64))?;
-
+        let checksums_offset = header.sections().self_hashes.offset;
         let mut tree_checksum = [0u8; 16];
         let mut chunk_hashes_checksum = [0u8; 16];
         let mut file_checksum = [0u8; 16];
 
-        reader.read_exact(&mut tree_checksum)?;
-        reader.read_exact(&mut chunk_hashes_checksum)?;
-        reader.read_exact(&mut file_checksum)?;
+        (|| {
+            reader.seek(SeekFrom::Start(checksums_offset))?;
+            reader.read_exact(&mut tree_checksum)?;
+            reader.read_exact(&mut chunk_hashes_checksum)?;
+            reader.read_exact(&mut file_checksum)
+        })()
+        .io_context(|| format!("Failed to read checksums at offset {checksums_offset}"))?;
 
         Ok(VPKChecksums {
             tree_checksum,
@@ -383,92 +997,186 @@ impl VPK {
     }
 
     /// Writes the VPK header
-    fn write_header<W: Write>(&self, writer: &mut W) -> Result<()> {
+    ///
+    /// Saved VPKs never have archive MD5s or a signature, even when the
+    /// opened file had them, since they would not match the new data.
+    fn write_header<W: Write>(&self, writer: &mut W, tree_length: u32) -> Result<()> {
         writer.write_all(&self.header.signature.to_le_bytes())?;
         writer.write_all(&(self.header.version as u32).to_le_bytes())?;
-        writer.write_all(&self.header.tree_length.to_le_bytes())?;
+        writer.write_all(&tree_length.to_le_bytes())?;
 
         if self.header.version == VPKVersion::V2 {
-            writer.write_all(&self.header.embed_chunk_length.unwrap_or(0).to_le_bytes())?;
-            writer.write_all(&self.header.chunk_hashes_length.unwrap_or(0).to_le_bytes())?;
-            writer.write_all(&self.header.self_hashes_length.unwrap_or(48).to_le_bytes())?;
-            writer.write_all(&self.header.signature_length.unwrap_or(0).to_le_bytes())?;
+            writer.write_all(&0u32.to_le_bytes())?; // embed_chunk_length, updated after the data
+            writer.write_all(&0u32.to_le_bytes())?; // chunk_hashes_length
+            writer.write_all(&48u32.to_le_bytes())?; // self_hashes_length
+            writer.write_all(&0u32.to_le_bytes())?; // signature_length
         }
 
         Ok(())
     }
 
-    /// Writes the file tree and embedded data
-    fn write_file_tree_and_data<W: Write + Seek>(&self, writer: &mut W) -> Result<u32> {
-        // Group files by extension and path
+    /// Groups entries by extension and directory using their raw names
+    fn group_entries(tree: &HashMap<String, FileMetadata>) -> Result<FileHashMap<'_>> {
         let mut grouped_files: FileHashMap = HashMap::new();
 
-        for (full_path, metadata) in &self.tree {
-            let (name, ext) = split_filename(full_path)?;
-            let path_part = if let Some(slash_pos) = name.rfind('/') {
-                name[..slash_pos].to_string()
-            } else {
-                " ".to_string() // Root directory
-            };
-            let name_part = if let Some(slash_pos) = name.rfind('/') {
-                name[slash_pos + 1..].to_string()
-            } else {
-                name
-            };
+        for (full_path, metadata) in tree {
+            let raw_path = metadata.raw_path.as_deref().unwrap_or(full_path.as_bytes());
+            let (path_part, name_part, ext) = split_raw_path(raw_path)
+                .ok_or_else(|| VpkError::MissingExtension(full_path.clone()))?;
 
             grouped_files
                 .entry(ext)
                 .or_default()
                 .entry(path_part)
                 .or_default()
-                .push((name_part, metadata));
+                .push((name_part, full_path, metadata));
         }
 
-        let data_start_offset = writer.stream_position()? as u32 + self.header.tree_length;
-        let mut current_data_offset = data_start_offset;
-        let mut embed_chunk_length = 0;
+        Ok(grouped_files)
+    }
+
+    /// Writes the file tree and embedded data, and the data of entries
+    /// assigned to archive chunks into chunk files next to `output_path`.
+    ///
+    /// The first `preload_length` bytes of each entry are stored in the tree.
+    /// Entries of an opened VPK are copied from its archive files. Chunk
+    /// files are added to `chunk_paths` as they are created.
+    fn write_file_tree_and_data(
+        &self,
+        writer: &mut BufWriter<File>,
+        grouped_files: &FileHashMap<'_>,
+        output_path: &Path,
+        progress: &ProgressTracker<'_>,
+        chunk_paths: &mut Vec<PathBuf>,
+    ) -> Result<u32> {
+        let mut embed_chunk_length = 0u32;
+        let mut chunk_lengths: BTreeMap<u16, u32> = BTreeMap::new();
 
         // Write file tree
-        for (ext, paths) in &grouped_files {
-            write_cstring(writer, ext)?;
+        for (ext, paths) in grouped_files {
+            write_cstring_bytes(writer, ext)?;
 
             for (path, files) in paths {
-                write_cstring(writer, path)?;
+                write_cstring_bytes(writer, path)?;
 
-                for (name, metadata) in files {
-                    write_cstring(writer, name)?;
+                for (name, _, metadata) in files {
+                    write_cstring_bytes(writer, name)?;
+
+                    let (preload, data) = Self::split_entry_data(metadata);
+                    let offset = if metadata.archive_index == EMBEDDED_ARCHIVE_INDEX {
+                        &mut embed_chunk_length
+                    } else {
+                        chunk_lengths.entry(metadata.archive_index).or_default()
+                    };
+                    let archive_offset = *offset;
+                    *offset += data.len() as u32;
 
-                    // Write metadata (as above)
                     writer.write_all(&metadata.crc32.to_le_bytes())?;
-                    writer.write_all(&0u16.to_le_bytes())?; // preload_length = 0  
-                    writer.write_all(&EMBEDDED_ARCHIVE_INDEX.to_le_bytes())?;
-                    writer.write_all(&(current_data_offset - data_start_offset).to_le_bytes())?;
-                    writer.write_all(&(metadata.preload.len() as u32).to_le_bytes())?;
+                    writer.write_all(&(preload.len() as u16).to_le_bytes())?;
+                    writer.write_all(&metadata.archive_index.to_le_bytes())?;
+                    writer.write_all(&archive_offset.to_le_bytes())?;
+                    writer.write_all(&(data.len() as u32).to_le_bytes())?;
                     writer.write_all(&METADATA_SUFFIX.to_le_bytes())?;
-
-                    current_data_offset += metadata.preload.len() as u32;
-                    embed_chunk_length += metadata.preload.len() as u32;
+                    writer.write_all(preload)?;
                 }
                 writer.write_all(&[0])?; // End of files in this path
             }
-            writer.write_all(&[0])?; // End of paths in this extension  
+            writer.write_all(&[0])?; // End of paths in this extension
         }
         writer.write_all(&[0])?; // End of tree
 
-        // Now write all the actual file data
+        // Now write all the actual file data, in the same order as the tree
+        let mut chunks: BTreeMap<u16, BufWriter<File>> = BTreeMap::new();
         for paths in grouped_files.values() {
             for files in paths.values() {
-                for (_name, metadata) in files {
-                    if !metadata.preload.is_empty() {
-                        writer.write_all(&metadata.preload)?;
-                    }
+                for (_name, path, metadata) in files {
+                    let (preload, data) = Self::split_entry_data(metadata);
+                    let bytes = preload.len() as u64 + data.len();
+
+                    let output = if metadata.archive_index == EMBEDDED_ARCHIVE_INDEX {
+                        &mut *writer
+                    } else if data.len() == 0 {
+                        progress.file_done(path, bytes)?;
+                        continue;
+                    } else {
+                        match chunks.entry(metadata.archive_index) {
+                            btree_map::Entry::Occupied(entry) => entry.into_mut(),
+                            btree_map::Entry::Vacant(entry) => {
+                                let chunk_path = VPKFile::resolve_archive_path(
+                                    output_path,
+                                    metadata.archive_index,
+                                );
+                                let file = File::create(&chunk_path).io_context(|| {
+                                    format!(
+                                        "Failed to create archive chunk: {}",
+                                        chunk_path.display()
+                                    )
+                                })?;
+                                chunk_paths.push(chunk_path);
+                                entry.insert(BufWriter::new(file))
+                            }
+                        }
+                    };
+                    self.write_entry_data(output, path, data)?;
+                    progress.file_done(path, bytes)?;
                 }
             }
         }
+        for chunk in chunks.values_mut() {
+            chunk.flush()?;
+        }
 
         Ok(embed_chunk_length)
     }
 
+    /// Writes the part of an entry stored outside the tree to `output`
+    fn write_entry_data(
+        &self,
+        output: &mut BufWriter<File>,
+        path: &str,
+        data: EntryData<'_>,
+    ) -> Result<()> {
+        match data {
+            EntryData::Memory(bytes) => output.write_all(bytes)?,
+            EntryData::Archive {
+                index,
+                offset,
+                length,
+            } => {
+                let source = self.archive(index)?;
+                output.flush()?;
+                let copied = copy_range(&source, offset, length, output.get_mut())?;
+                if copied < length {
+                    return Err(VpkError::io(
+                        format!("Failed to read {path}"),
+                        io::ErrorKind::UnexpectedEof.into(),
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Splits an entry into the bytes stored in the tree and the data stored
+    /// in the embedded data or a chunk.
+    ///
+    /// Unsaved entries hold all their data in memory, while entries of an
+    /// opened VPK only hold the preload bytes.
+    fn split_entry_data(metadata: &FileMetadata) -> (&[u8], EntryData<'_>) {
+        let preload_length = (metadata.preload_length as usize).min(metadata.preload.len());
+        let (preload, rest) = metadata.preload.split_at(preload_length);
+        let data = if rest.len() as u64 == metadata.file_length as u64 {
+            EntryData::Memory(rest)
+        } else {
+            EntryData::Archive {
+                index: metadata.archive_index,
+                offset: metadata.archive_offset as u64,
+                length: metadata.file_length as u64,
+            }
+        };
+        (preload, data)
+    }
+
     /// Writes checksums for V2 files
     fn write_checksums<W: Write + Seek>(
         &self,
@@ -489,43 +1197,19 @@ impl VPK {
     }
 
     /// Calculates the tree length for the given file set
-    fn calculate_tree_length(tree: &HashMap<String, FileMetadata>) -> u32 {
+    fn calculate_tree_length(grouped_files: &FileHashMap) -> u32 {
         let mut length = 1; // Final null terminator
 
-        // Group by extension for calculation
-        let mut extensions: HashMap<String, HashMap<String, Vec<String>>> = HashMap::new();
-
-        for full_path in tree.keys() {
-            if let Ok((name, ext)) = split_filename(full_path) {
-                let path_part = if let Some(slash_pos) = name.rfind('/') {
-                    name[..slash_pos].to_string()
-                } else {
-                    " ".to_string()
-                };
-                let name_part = if let Some(slash_pos) = name.rfind('/') {
-                    name[slash_pos + 1..].to_string()
-                } else {
-                    name
-                };
-
-                extensions
-                    .entry(ext)
-                    .or_default()
-                    .entry(path_part)
-                    .or_default()
-                    .push(name_part);
-            }
-        }
-
-        for (ext, paths) in extensions {
-            length += cstring_length(&ext) as u32;
+        for (ext, paths) in grouped_files {
+            length += ext.len() as u32 + 1;
 
             for (path, names) in paths {
-                length += cstring_length(&path) as u32;
+                length += path.len() as u32 + 1;
 
-                for name in names {
-                    length += cstring_length(&name) as u32;
-                    length += 18; // Metadata size only, no preload data in tree
+                for (name, _, metadata) in names {
+                    length += name.len() as u32 + 1;
+                    length += 18; // Metadata size
+                    length += Self::split_entry_data(metadata).0.len() as u32;
                 }
                 length += 1; // Path terminator
             }
@@ -535,34 +1219,255 @@ impl VPK {
         length
     }
 
+    /// Gets the path lookup mode
+    pub fn path_lookup(&self) -> PathLookup {
+        self.path_lookup
+    }
+
+    /// Sets how lookup paths are matched against entries
+    pub fn set_path_lookup(&mut self, path_lookup: PathLookup) {
+        self.path_lookup = path_lookup;
+    }
+
+    /// Resolves a lookup path to the entry path stored in the VPK.
+    ///
+    /// An exact match always wins. In [`PathLookup::Normalized`] mode an
+    /// error is returned if several entries differ only in case.
+    pub fn resolve_path(&self, path: &str) -> Result<&str> {
+        if let Some((key, _)) = self.tree()?.get_key_value(path) {
+            return Ok(key);
+        }
+
+        if self.path_lookup == PathLookup::Exact {
+            return Err(VpkError::FileNotFound(path.to_string()));
+        }
+
+        let candidates = self
+            .lookup_index()?
+            .get(&normalize_lookup_path(path))
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+
+        match candidates {
+            [] => Err(VpkError::FileNotFound(path.to_string())),
+            [key] => Ok(key),
+            _ => Err(VpkError::AmbiguousPath {
+                path: path.to_string(),
+                candidates: candidates.to_vec(),
+            }),
+        }
+    }
+
+    /// Resolves the raw bytes of an entry name, as stored in the tree, to its entry path
+    pub fn resolve_raw_path(&self, raw_path: &[u8]) -> Result<&str> {
+        let not_found = || VpkError::FileNotFound(display_name(raw_path).into_owned());
+        let path = decode_name(raw_path, self.name_encoding).map_err(|_| not_found())?;
+
+        match self.tree()?.get_key_value(path.as_ref()) {
+            Some((key, metadata))
+                if metadata.raw_path.as_deref().unwrap_or(key.as_bytes()) == raw_path =>
+            {
+                Ok(key)
+            }
+            _ => Err(not_found()),
+        }
+    }
+
+    /// Gets the raw bytes of an entry name as stored in the tree
+    ///
+    /// Like [`VPK::contains`], a lazily opened VPK whose tree fails to parse
+    /// has no entries here.
+    pub fn raw_path(&self, path: &str) -> Option<&[u8]> {
+        let (key, metadata) = self.tree().ok()?.get_key_value(path)?;
+        Some(metadata.raw_path.as_deref().unwrap_or(key.as_bytes()))
+    }
+
+    /// Lists groups of entries that collide under normalized lookup
+    pub fn ambiguous_paths(&self) -> Result<Vec<&[String]>> {
+        let mut groups: Vec<_> = self
+            .lookup_index()?
+            .values()
+            .filter(|paths| paths.len() > 1)
+            .map(Vec::as_slice)
+            .collect();
+        groups.sort();
+        Ok(groups)
+    }
+
     /// Gets a file from the VPK
     pub fn get_file(&self, path: &str) -> Result<VPKFile> {
-        let metadata = self
-            .tree
-            .get(path)
-            .ok_or_else(|| anyhow::anyhow!("File not found: {}", path))?;
+        let key = self.resolve_path(path)?;
+        let metadata = &self.tree()?[key];
 
         let vpk_path = self
             .path
             .as_ref()
-            .ok_or_else(|| anyhow::anyhow!("Cannot get file from unsaved VPK"))?;
+            .ok_or(VpkError::NotSaved("get file from"))?;
+
+        let archive = if metadata.file_length > 0 {
+            Some(self.archive(metadata.archive_index)?)
+        } else {
+            None
+        };
+        Ok(VPKFile::with_archive(
+            vpk_path,
+            key.to_string(),
+            metadata.clone(),
+            archive,
+        ))
+    }
+
+    /// Gets the shared handle of the file holding archive `index`, opening
+    /// it on first use
+    pub(crate) fn archive(&self, index: u16) -> Result<Arc<File>> {
+        let vpk_path = self.path.as_ref().ok_or(VpkError::NotSaved("read from"))?;
+        self.archives.get(vpk_path, index)
+    }
+
+    /// Reads the whole contents of a file in the VPK.
+    ///
+    /// Unlike [`VPK::get_file`] this needs no cursor; archive data is read
+    /// through the VPK's shared handles. Entries of a VPK that has not been
+    /// saved are read from memory.
+    pub fn read(&self, path: &str) -> Result<Vec<u8>> {
+        let (key, metadata) = self.lookup(path)?;
+        let mut data =
+            Vec::with_capacity(metadata.total_length().min(MAX_READ_RESERVATION) as usize);
+        self.copy_entry_range(key, metadata, 0, u64::MAX, &mut data)?;
+        Ok(data)
+    }
+
+    /// Reads the whole contents of a file in the VPK as a UTF-8 string
+    pub fn read_to_string(&self, path: &str) -> Result<String> {
+        let (key, metadata) = self.lookup(path)?;
+        let mut data =
+            Vec::with_capacity(metadata.total_length().min(MAX_READ_RESERVATION) as usize);
+        self.copy_entry_range(key, metadata, 0, u64::MAX, &mut data)?;
+        String::from_utf8(data).map_err(|e| VpkError::InvalidUtf8 {
+            what: format!("file {key}"),
+            source: e.utf8_error(),
+        })
+    }
+
+    /// Reads up to `length` bytes of a file in the VPK starting at `offset`.
+    ///
+    /// The range is cut short at the end of the file, so an `offset` past
+    /// the end gives no bytes.
+    pub fn read_range(&self, path: &str, offset: u64, length: usize) -> Result<Vec<u8>> {
+        let (key, metadata) = self.lookup(path)?;
+        let available = metadata.total_length().saturating_sub(offset);
+        let mut data =
+            Vec::with_capacity(available.min(length as u64).min(MAX_READ_RESERVATION) as usize);
+        self.copy_entry_range(key, metadata, offset, length as u64, &mut data)?;
+        Ok(data)
+    }
+
+    /// Writes the contents of a file in the VPK to `writer` and returns the
+    /// number of bytes written
+    pub fn copy_to<W: Write + ?Sized>(&self, path: &str, writer: &mut W) -> Result<u64> {
+        let (key, metadata) = self.lookup(path)?;
+        self.copy_entry_range(key, metadata, 0, u64::MAX, writer)
+    }
 
-        VPKFile::new(vpk_path, path.to_string(), metadata.clone())
+    /// Resolves `path` to its entry path and metadata
+    fn lookup(&self, path: &str) -> Result<(&str, &FileMetadata)> {
+        let key = self.resolve_path(path)?;
+        Ok((key, &self.tree()?[key]))
+    }
+
+    /// Writes up to `length` bytes of an entry starting at `offset` to
+    /// `writer`, preload bytes first
+    fn copy_entry_range<W: Write + ?Sized>(
+        &self,
+        key: &str,
+        metadata: &FileMetadata,
+        offset: u64,
+        length: u64,
+        writer: &mut W,
+    ) -> Result<u64> {
+        let context = || format!("Failed to read {key}");
+        let (preload, data) = Self::split_entry_data(metadata);
+        let end = offset.saturating_add(length);
+
+        let preload_length = preload.len() as u64;
+        let preload_range = offset.min(preload_length) as usize..end.min(preload_length) as usize;
+        writer
+            .write_all(&preload[preload_range.clone()])
+            .io_context(context)?;
+        let mut written = preload_range.len() as u64;
+
+        // Offsets into the part stored outside the tree
+        let data_start = offset.saturating_sub(preload_length).min(data.len());
+        let data_end = end.saturating_sub(preload_length).min(data.len());
+        match data {
+            EntryData::Memory(bytes) => {
+                writer
+                    .write_all(&bytes[data_start as usize..data_end as usize])
+                    .io_context(context)?;
+                written += data_end - data_start;
+            }
+            EntryData::Archive { .. } if data_start == data_end => {}
+            EntryData::Archive {
+                index,
+                offset: archive_offset,
+                ..
+            } => {
+                let archive = self.archive(index)?;
+                let mut range =
+                    ArchiveRange::new(&archive, archive_offset + data_start, data_end - data_start);
+                let copied = io::copy(&mut range, writer).io_context(context)?;
+                if copied < data_end - data_start {
+                    return Err(VpkError::io(context(), io::ErrorKind::UnexpectedEof.into()));
+                }
+                written += copied;
+            }
+        }
+
+        Ok(written)
     }
 
     /// Checks if a file exists in the VPK
+    ///
+    /// For lazily opened VPKs this reads the file tree; a tree that fails to
+    /// parse is treated as empty. Use [`VPK::index`] to see the error.
     pub fn contains(&self, path: &str) -> bool {
-        self.tree.contains_key(path)
+        match self.path_lookup {
+            PathLookup::Exact => self.tree().is_ok_and(|tree| tree.contains_key(path)),
+            PathLookup::Normalized => self
+                .lookup_index()
+                .is_ok_and(|index| index.contains_key(&normalize_lookup_path(path))),
+        }
     }
 
     /// Gets an iterator over all file paths
+    ///
+    /// Yields nothing for a lazily opened VPK whose tree fails to parse; see
+    /// [`VPK::index`].
     pub fn file_paths(&self) -> impl Iterator<Item = &String> {
-        self.tree.keys()
+        self.tree().ok().into_iter().flat_map(HashMap::keys)
     }
 
     /// Gets the number of files in the VPK
+    ///
+    /// Returns 0 for a lazily opened VPK whose tree fails to parse; see
+    /// [`VPK::index`].
     pub fn file_count(&self) -> usize {
-        self.tree.len()
+        self.tree().map_or(0, HashMap::len)
+    }
+
+    /// Gets the path of the directory file the VPK was opened from, or `None` if it was built in memory
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Gets the VPK header
+    pub fn header(&self) -> &VPKHeader {
+        &self.header
+    }
+
+    /// Gets the stored checksums (V2 only)
+    pub fn checksums(&self) -> Option<&VPKChecksums> {
+        self.checksums.as_ref()
     }
 
     /// Gets the VPK version
@@ -573,11 +1478,13 @@ impl VPK {
     /// Verifies the VPK checksums (V2 only)
     pub fn verify(&self) -> Result<bool> {
         if self.header.version != VPKVersion::V2 || self.checksums.is_none() {
-            bail!("Verification only supported for VPK V2 with checksums");
+            return Err(VpkError::Unsupported(
+                "Verification only supported for VPK V2 with checksums",
+            ));
         }
 
         // let path = self.path.as_ref()
-        //     .ok_or_else(|| anyhow::anyhow!("Cannot verify unsaved VPK"))?;
+        //     .ok_or(VpkError::NotSaved("verify"))?;
 
         // let mut file = BufReader::new(File::open(path)?);
         // let checksums = self.checksums.as_ref().unwrap();
@@ -607,16 +1514,20 @@ impl VPK {
 
     /// Lists all files in the VPK
     pub fn list_files(&self) -> Vec<&String> {
-        self.tree.keys().collect()
+        self.file_paths().collect()
     }
 }
 
 impl std::fmt::Debug for VPK {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("VPK")
+        let mut debug = f.debug_struct("VPK");
+        debug
             .field("path", &self.path)
-            .field("version", &self.header.version)
-            .field("file_count", &self.tree.len())
-            .finish()
+            .field("version", &self.header.version);
+        match self.tree.get() {
+            Some(tree) => debug.field("file_count", &tree.len()),
+            None => debug.field("file_count", &"<not indexed>"),
+        };
+        debug.finish()
     }
 }
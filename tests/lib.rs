@@ -33,6 +33,18 @@ fn create_test_directory(base_path: &std::path::Path) -> Result<()> {
     Ok(())
 }
 
+/// Packs the files of [`create_test_directory`], created in `temp_dir/source`,
+/// from a JSON manifest into `temp_dir/pak01_dir.vpk` and its chunks
+fn build_chunked_vpk(temp_dir: &TempDir, manifest: &str) -> Result<std::path::PathBuf> {
+    create_test_directory(&temp_dir.path().join("source"))?;
+    let manifest_path = temp_dir.path().join("files.json");
+    fs::write(&manifest_path, manifest)?;
+
+    let vpk_path = temp_dir.path().join("pak01_dir.vpk");
+    VPK::from_manifest(&manifest_path)?.save(&vpk_path)?;
+    Ok(vpk_path)
+}
+
 #[test]
 fn test_create_and_read_vpk() -> Result<()> {
     let temp_dir = TempDir::new()?;
@@ -295,3 +307,1240 @@ fn test_special_characters_in_filenames() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_open_header_only() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let source_dir = temp_dir.path().join("source");
+    let vpk_path = temp_dir.path().join("test.vpk");
+
+    create_test_directory(&source_dir)?;
+    VPK::from_directory(&source_dir)?.save(&vpk_path)?;
+
+    let info = VPK::open_header(&vpk_path)?;
+    assert_eq!(info.header.version, valve_pak::vpk::VPKVersion::V2);
+    assert_eq!(info.file_size, fs::metadata(&vpk_path)?.len());
+    assert!(info.checksums.is_some());
+
+    // Sections are laid out back to back and end at the file size
+    let sections = info.sections;
+    assert_eq!(sections.tree.offset, 28);
+    assert_eq!(sections.embedded_data.offset, sections.tree.end());
+    assert_eq!(sections.self_hashes.length, 48);
+    assert_eq!(sections.signature.end(), info.file_size);
+
+    // Section lengths that overflow u32 are rejected instead of wrapping
+    let mut bytes = fs::read(&vpk_path)?;
+    bytes[12..16].copy_from_slice(&u32::MAX.to_le_bytes());
+    bytes[16..20].copy_from_slice(&u32::MAX.to_le_bytes());
+    let hostile_path = temp_dir.path().join("hostile.vpk");
+    fs::write(&hostile_path, &bytes)?;
+    assert!(matches!(
+        VPK::open_header(&hostile_path),
+        Err(valve_pak::VpkError::OutOfBounds { .. })
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_lazy_open() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let source_dir = temp_dir.path().join("source");
+    let vpk_path = temp_dir.path().join("test.vpk");
+
+    create_test_directory(&source_dir)?;
+    VPK::from_directory(&source_dir)?.save(&vpk_path)?;
+
+    let vpk = VPK::open_lazy(&vpk_path)?;
+    assert!(!vpk.is_indexed());
+
+    let content = vpk.get_file("readme.txt")?.read_all_string()?;
+    assert_eq!(content, "This is a test readme file.\nSecond line.\n");
+    assert!(vpk.is_indexed());
+    assert_eq!(vpk.file_count(), 5);
+    assert!(vpk.index().is_ok());
+
+    // A tree that fails to parse is reported by `index` and looks empty elsewhere
+    let mut bytes = fs::read(&vpk_path)?;
+    let tree_length = u32::from_le_bytes(bytes[8..12].try_into()?) as usize;
+    bytes[28..28 + tree_length].fill(0xff);
+    fs::write(&vpk_path, &bytes)?;
+    let vpk = VPK::open_lazy(&vpk_path)?;
+    assert!(vpk.index().is_err());
+    assert_eq!(vpk.file_count(), 0);
+    assert_eq!(vpk.file_paths().count(), 0);
+    assert_eq!((&vpk).into_iter().count(), 0);
+    assert!(!vpk.contains("readme.txt"));
+    let options = valve_pak::ExtractOptions::new();
+    assert!(
+        vpk.extract_all(temp_dir.path().join("out"), &options)
+            .is_err()
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_normalized_path_lookup() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let source_dir = temp_dir.path().join("source");
+    let vpk_path = temp_dir.path().join("test.vpk");
+
+    create_test_directory(&source_dir)?;
+    fs::write(source_dir.join("Config.cfg"), "different case")?;
+    VPK::from_directory(&source_dir)?.save(&vpk_path)?;
+
+    // Exact lookups are the default
+    let vpk = VPK::open(&vpk_path)?;
+    assert!(!vpk.contains("Scripts\\Test.LUA"));
+
+    let vpk = valve_pak::OpenOptions::new()
+        .path_lookup(valve_pak::PathLookup::Normalized)
+        .open(&vpk_path)?;
+    assert!(vpk.contains("Scripts\\Test.LUA"));
+    assert_eq!(vpk.resolve_path("./scripts//TEST.lua")?, "scripts/test.lua");
+    assert_eq!(
+        vpk.get_file("TEXTURES/test.dds")?.filepath(),
+        "textures/test.dds"
+    );
+
+    // Exact matches win, otherwise entries differing only in case are ambiguous
+    assert_eq!(vpk.resolve_path("Config.cfg")?, "Config.cfg");
+    assert!(vpk.resolve_path("CONFIG.CFG").is_err());
+    assert_eq!(
+        vpk.ambiguous_paths()?,
+        vec![&["Config.cfg".to_string(), "config.cfg".to_string()][..]]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_typed_errors() -> Result<()> {
+    use valve_pak::VpkError;
+
+    let temp_dir = TempDir::new()?;
+    let source_dir = temp_dir.path().join("source");
+    let vpk_path = temp_dir.path().join("test.vpk");
+
+    create_test_directory(&source_dir)?;
+    VPK::from_directory(&source_dir)?.save(&vpk_path)?;
+
+    let vpk = VPK::open(&vpk_path)?;
+    assert!(matches!(
+        vpk.get_file("missing.txt"),
+        Err(VpkError::FileNotFound(path)) if path == "missing.txt"
+    ));
+
+    // Corrupt the signature
+    let mut bytes = fs::read(&vpk_path)?;
+    let bad_signature_path = temp_dir.path().join("bad_signature.vpk");
+    bytes[0] = 0;
+    fs::write(&bad_signature_path, &bytes)?;
+    assert!(matches!(
+        VPK::open(&bad_signature_path),
+        Err(VpkError::InvalidSignature { found: 0x55aa1200 })
+    ));
+
+    // Cut the file in the middle of the tree
+    let truncated_path = temp_dir.path().join("truncated.vpk");
+    fs::write(&truncated_path, &fs::read(&vpk_path)?[..40])?;
+    let err = VPK::open(&truncated_path).unwrap_err();
+    assert!(matches!(err, VpkError::TruncatedTree { offset: 40 }));
+    assert_eq!(err.to_string(), "Truncated file tree at offset 40");
+
+    // I/O errors keep the path and chain the underlying error
+    let err = VPK::open(temp_dir.path().join("nonexistent.vpk")).unwrap_err();
+    assert!(err.to_string().contains("nonexistent.vpk"));
+    assert!(std::error::Error::source(&err).is_some());
+
+    Ok(())
+}
+
+/// Builds a V1 VPK holding a single root entry `name.ext` with the given metadata
+fn build_v1_vpk(
+    name: &[u8],
+    preload_length: u16,
+    archive_offset: u32,
+    file_length: u32,
+) -> Vec<u8> {
+    build_v1_vpk_entries(&[(name, preload_length, EMBEDDED, archive_offset, file_length)])
+}
+
+/// Archive index of entries stored in the directory file
+const EMBEDDED: u16 = 0x7fff;
+
+/// Builds a V1 VPK holding root entries `name.ext` with the given
+/// `(name, preload_length, archive_index, archive_offset, file_length)`
+fn build_v1_vpk_entries(entries: &[(&[u8], u16, u16, u32, u32)]) -> Vec<u8> {
+    let mut tree = Vec::new();
+    tree.extend_from_slice(b"ext\0 \0");
+    for &(name, preload_length, archive_index, archive_offset, file_length) in entries {
+        tree.extend_from_slice(name);
+        tree.push(0);
+        tree.extend_from_slice(&0u32.to_le_bytes()); // crc32
+        tree.extend_from_slice(&preload_length.to_le_bytes());
+        tree.extend_from_slice(&archive_index.to_le_bytes());
+        tree.extend_from_slice(&archive_offset.to_le_bytes());
+        tree.extend_from_slice(&file_length.to_le_bytes());
+        tree.extend_from_slice(&0xffffu16.to_le_bytes());
+    }
+    tree.extend_from_slice(b"\0\0\0");
+
+    let mut vpk = Vec::new();
+    vpk.extend_from_slice(&0x55aa1234u32.to_le_bytes());
+    vpk.extend_from_slice(&1u32.to_le_bytes());
+    vpk.extend_from_slice(&(tree.len() as u32).to_le_bytes());
+    vpk.extend_from_slice(&tree);
+    vpk
+}
+
+#[test]
+fn test_hostile_vpk_rejected() -> Result<()> {
+    use std::io::Cursor;
+    use valve_pak::VpkError;
+    use valve_pak::vpk::Limits;
+
+    // A well-formed buffer with 4 bytes of embedded data parses
+    let mut bytes = build_v1_vpk(b"file", 0, 0, 4);
+    bytes.extend_from_slice(b"data");
+    let vpk = VPK::from_reader(&mut Cursor::new(&bytes))?;
+    assert!(vpk.contains("file.ext"));
+
+    // Embedded data past the end of the file
+    let bytes = build_v1_vpk(b"file", 0, 0, 4);
+    assert!(matches!(
+        VPK::from_reader(&mut Cursor::new(&bytes)),
+        Err(VpkError::OutOfBounds { .. })
+    ));
+
+    // Offsets that overflow u32 once the header and tree are added
+    let bytes = build_v1_vpk(b"file", 0, u32::MAX, 0);
+    assert!(matches!(
+        VPK::from_reader(&mut Cursor::new(&bytes)),
+        Err(VpkError::OutOfBounds { .. })
+    ));
+
+    // Preload running past the end of the tree
+    let bytes = build_v1_vpk(b"file", 1000, 0, 0);
+    assert!(matches!(
+        VPK::from_reader(&mut Cursor::new(&bytes)),
+        Err(VpkError::TruncatedTree { .. })
+    ));
+
+    // Tree length larger than the file
+    let mut bytes = build_v1_vpk(b"file", 0, 0, 0);
+    bytes[8..12].copy_from_slice(&u32::MAX.to_le_bytes());
+    let limits = Limits {
+        max_tree_length: u32::MAX,
+        ..Limits::default()
+    };
+    assert!(matches!(
+        valve_pak::OpenOptions::new()
+            .limits(limits)
+            .from_reader(&mut Cursor::new(&bytes)),
+        Err(VpkError::TruncatedTree { .. })
+    ));
+    assert!(matches!(
+        VPK::from_reader(&mut Cursor::new(&bytes)),
+        Err(VpkError::LimitExceeded { .. })
+    ));
+
+    // Configurable entry count and string length limits
+    let bytes = build_v1_vpk(b"a_rather_long_name", 0, 0, 0);
+    let strict = |limits: Limits| {
+        valve_pak::OpenOptions::new()
+            .limits(limits)
+            .from_reader(&mut Cursor::new(&bytes))
+    };
+    assert!(matches!(
+        strict(Limits {
+            max_entries: 0,
+            ..Limits::default()
+        }),
+        Err(VpkError::LimitExceeded { .. })
+    ));
+    assert!(matches!(
+        strict(Limits {
+            max_string_length: 8,
+            ..Limits::default()
+        }),
+        Err(VpkError::LimitExceeded { .. })
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_extract_file_stays_inside_output_dir() -> Result<()> {
+    use valve_pak::{ExtractOptions, UnsafePathPolicy, VpkError};
+
+    let temp_dir = TempDir::new()?;
+    let source_dir = temp_dir.path().join("source");
+    let vpk_path = temp_dir.path().join("test.vpk");
+    let extract_dir = temp_dir.path().join("extracted");
+
+    create_test_directory(&source_dir)?;
+    VPK::from_directory(&source_dir)?.save(&vpk_path)?;
+    let vpk = VPK::open(&vpk_path)?;
+
+    let written = vpk.extract_file("scripts/test.lua", &extract_dir, &ExtractOptions::new())?;
+    assert_eq!(written, extract_dir.join("scripts/test.lua"));
+    assert_eq!(fs::read_to_string(written)?, "print('Hello from Lua')\n");
+
+    // A hostile archive with an entry pointing outside the output directory
+    let mut bytes = build_v1_vpk(b"../escape", 0, 0, 4);
+    bytes.extend_from_slice(b"data");
+    let hostile_path = temp_dir.path().join("hostile.vpk");
+    fs::write(&hostile_path, &bytes)?;
+    let hostile = VPK::open(&hostile_path)?;
+
+    assert!(matches!(
+        hostile.extract_file("../escape.ext", &extract_dir, &ExtractOptions::new()),
+        Err(VpkError::UnsafePath { .. })
+    ));
+
+    let sanitize = ExtractOptions::new().unsafe_paths(UnsafePathPolicy::Sanitize);
+    let written = hostile.extract_file("../escape.ext", &extract_dir, &sanitize)?;
+    assert_eq!(written, extract_dir.join("escape.ext"));
+    assert_eq!(fs::read(written)?, b"data");
+    assert!(!temp_dir.path().join("escape.ext").exists());
+
+    // Entries that sanitize to the same file are never written concurrently
+    let mut bytes =
+        build_v1_vpk_entries(&[(b"./twin", 0, EMBEDDED, 0, 4), (b"twin", 0, EMBEDDED, 4, 4)]);
+    bytes.extend_from_slice(b"datatwin");
+    fs::write(&hostile_path, &bytes)?;
+    let hostile = VPK::open(&hostile_path)?;
+    let twin_dir = temp_dir.path().join("twins");
+    assert!(matches!(
+        hostile.extract_all(&twin_dir, &ExtractOptions::new().jobs(2)),
+        Err(VpkError::UnsafePath { .. })
+    ));
+    assert_eq!(hostile.extract_all(&twin_dir, &sanitize.jobs(2))?, 1);
+    assert_eq!(fs::read(twin_dir.join("twin.ext"))?, b"data");
+
+    // Names differing only in case collide on case-insensitive file systems
+    let mut bytes =
+        build_v1_vpk_entries(&[(b"Twin", 0, EMBEDDED, 0, 4), (b"twin", 0, EMBEDDED, 4, 4)]);
+    bytes.extend_from_slice(b"datatwin");
+    fs::write(&hostile_path, &bytes)?;
+    let hostile = VPK::open(&hostile_path)?;
+    let case_dir = temp_dir.path().join("cases");
+    let windows = ExtractOptions::new().windows_names(true).jobs(2);
+    assert!(matches!(
+        hostile.extract_all(&case_dir, &windows),
+        Err(VpkError::UnsafePath { .. })
+    ));
+    let windows = windows.unsafe_paths(UnsafePathPolicy::Sanitize);
+    assert_eq!(hostile.extract_all(&case_dir, &windows)?, 1);
+    assert_eq!(fs::read(case_dir.join("Twin.ext"))?, b"data");
+
+    Ok(())
+}
+
+#[test]
+fn test_non_utf8_entry_names() -> Result<()> {
+    use std::io::Cursor;
+    use valve_pak::VpkError;
+    use valve_pak::utils::NameEncoding;
+
+    let temp_dir = TempDir::new()?;
+    let bytes = build_v1_vpk(b"caf\xe9", 0, 0, 0);
+
+    // Strict decoding keeps the old behaviour
+    assert!(matches!(
+        valve_pak::OpenOptions::new()
+            .name_encoding(NameEncoding::Utf8)
+            .from_reader(&mut Cursor::new(&bytes)),
+        Err(VpkError::InvalidUtf8 { .. })
+    ));
+
+    // By default the name falls back to Windows-1252 and keeps its raw bytes
+    let vpk_path = temp_dir.path().join("legacy.vpk");
+    fs::write(&vpk_path, &bytes)?;
+    let vpk = VPK::open(&vpk_path)?;
+    assert!(vpk.contains("café.ext"));
+    assert_eq!(vpk.raw_path("café.ext"), Some(&b"caf\xe9.ext"[..]));
+    assert_eq!(vpk.resolve_raw_path(b"caf\xe9.ext")?, "café.ext");
+
+    // Saving writes the original bytes back
+    let resaved_path = temp_dir.path().join("resaved.vpk");
+    vpk.save(&resaved_path)?;
+    let resaved = VPK::open(&resaved_path)?;
+    assert_eq!(resaved.raw_path("café.ext"), Some(&b"caf\xe9.ext"[..]));
+
+    // Raw names that decode to the same path are reported, not merged
+    let bytes = build_v1_vpk_entries(&[
+        (b"caf\xe9", 0, EMBEDDED, 0, 0),
+        ("café".as_bytes(), 0, EMBEDDED, 0, 0),
+    ]);
+    assert!(matches!(
+        VPK::from_reader(&mut Cursor::new(&bytes)),
+        Err(VpkError::NameCollision { .. })
+    ));
+    let bytes =
+        build_v1_vpk_entries(&[(b"a\xff", 0, EMBEDDED, 0, 0), (b"a\xfe", 0, EMBEDDED, 0, 0)]);
+    assert!(matches!(
+        valve_pak::OpenOptions::new()
+            .name_encoding(NameEncoding::Lossy)
+            .from_reader(&mut Cursor::new(&bytes)),
+        Err(VpkError::NameCollision { .. })
+    ));
+
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_non_utf8_file_names_round_trip() -> Result<()> {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+    use valve_pak::ExtractOptions;
+
+    let temp_dir = TempDir::new()?;
+    let source_dir = temp_dir.path().join("source");
+    let vpk_path = temp_dir.path().join("test.vpk");
+    let extract_dir = temp_dir.path().join("extracted");
+
+    fs::create_dir_all(&source_dir)?;
+    let raw_name = OsStr::from_bytes(b"caf\xe9.txt");
+    fs::write(source_dir.join(raw_name), "latin-1 name")?;
+
+    VPK::from_directory(&source_dir)?.save(&vpk_path)?;
+    let vpk = VPK::open(&vpk_path)?;
+    assert_eq!(vpk.raw_path("café.txt"), Some(raw_name.as_bytes()));
+
+    vpk.extract_file("café.txt", &extract_dir, &ExtractOptions::new())?;
+    assert_eq!(
+        fs::read_to_string(extract_dir.join(raw_name))?,
+        "latin-1 name"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_entries() -> Result<()> {
+    use valve_pak::EntryLocation;
+
+    let temp_dir = TempDir::new()?;
+    let source_dir = temp_dir.path().join("source");
+    let vpk_path = temp_dir.path().join("test.vpk");
+
+    create_test_directory(&source_dir)?;
+    fs::write(source_dir.join("empty.txt"), "")?;
+
+    // Sizes are known before the VPK is saved
+    let unsaved = VPK::from_directory(&source_dir)?;
+    assert_eq!(unsaved.entry("textures/test.dds")?.size(), 256);
+
+    unsaved.save(&vpk_path)?;
+    let vpk = VPK::open(&vpk_path)?;
+    assert_eq!(vpk.entries().len(), vpk.file_count());
+
+    let entry = vpk.entry("textures/test.dds")?;
+    assert_eq!(entry.path(), "textures/test.dds");
+    assert_eq!(entry.directory(), "textures");
+    assert_eq!(entry.file_name(), "test.dds");
+    assert_eq!(entry.extension(), "dds");
+    assert_eq!(entry.size(), 256);
+    assert_eq!(entry.crc32(), crc32fast::hash(&[0u8; 256]));
+    assert!(matches!(entry.location(), EntryLocation::Embedded { .. }));
+
+    let empty = vpk.entry("empty.txt")?;
+    assert_eq!(empty.directory(), "");
+    assert_eq!(empty.location(), EntryLocation::PreloadOnly);
+
+    let mut paths: Vec<_> = (&vpk).into_iter().map(|entry| entry.path()).collect();
+    paths.sort();
+    let mut expected: Vec<_> = vpk.file_paths().map(String::as_str).collect();
+    expected.sort();
+    assert_eq!(paths, expected);
+
+    Ok(())
+}
+
+#[test]
+fn test_browse_directories() -> Result<()> {
+    use valve_pak::{PathLookup, VpkError};
+
+    let temp_dir = TempDir::new()?;
+    let source_dir = temp_dir.path().join("source");
+    let vpk_path = temp_dir.path().join("test.vpk");
+
+    create_test_directory(&source_dir)?;
+    fs::create_dir_all(source_dir.join("materials/models/props"))?;
+    fs::write(source_dir.join("materials/models/props/crate.vmt"), "a")?;
+    fs::write(source_dir.join("materials/models/props/crate.vtf"), "b")?;
+    fs::write(source_dir.join("materials/models/barrel.vmt"), "c")?;
+    VPK::from_directory(&source_dir)?.save(&vpk_path)?;
+    let mut vpk = VPK::open(&vpk_path)?;
+
+    let root: Vec<_> = vpk
+        .read_dir("")?
+        .iter()
+        .map(|entry| (entry.name(), entry.is_dir()))
+        .collect();
+    assert_eq!(
+        root,
+        [
+            ("materials", true),
+            ("scripts", true),
+            ("sounds", true),
+            ("textures", true),
+            ("config.cfg", false),
+            ("readme.txt", false),
+        ]
+    );
+
+    // Directories that only hold subdirectories are listed too
+    let materials: Vec<_> = vpk
+        .read_dir("materials")?
+        .iter()
+        .map(|entry| entry.path())
+        .collect();
+    assert_eq!(materials, ["materials/models"]);
+    assert!(matches!(
+        vpk.read_dir("missing"),
+        Err(VpkError::DirectoryNotFound(_))
+    ));
+
+    let walked: Vec<_> = vpk.walk("materials/models/")?.map(|e| e.path()).collect();
+    assert_eq!(
+        walked,
+        [
+            "materials/models/barrel.vmt",
+            "materials/models/props/crate.vmt",
+            "materials/models/props/crate.vtf",
+        ]
+    );
+    assert_eq!(vpk.walk("")?.count(), vpk.file_count());
+
+    assert_eq!(
+        vpk.directories()?.collect::<Vec<_>>(),
+        [
+            "materials",
+            "materials/models",
+            "materials/models/props",
+            "scripts",
+            "sounds",
+            "textures",
+        ]
+    );
+    assert_eq!(
+        vpk.extensions()?.collect::<Vec<_>>(),
+        ["cfg", "dds", "lua", "txt", "vmt", "vtf", "wav"]
+    );
+    assert_eq!(vpk.files_with_extension(".vmt")?.count(), 2);
+    assert_eq!(vpk.files_with_extension("VMT")?.count(), 0);
+
+    vpk.set_path_lookup(PathLookup::Normalized);
+    assert_eq!(vpk.files_with_extension("VMT")?.count(), 2);
+    assert_eq!(vpk.read_dir("Materials\\Models")?.len(), 2);
+    assert_eq!(vpk.walk("MATERIALS/models/")?.count(), 3);
+
+    // Directories differing only in case are reported like ambiguous files
+    fs::create_dir_all(source_dir.join("Sounds"))?;
+    fs::write(source_dir.join("Sounds/loud.wav"), "d")?;
+    let mut vpk = VPK::from_directory(&source_dir)?;
+    vpk.set_path_lookup(PathLookup::Normalized);
+    assert_eq!(vpk.read_dir("sounds")?.len(), 1);
+    assert!(matches!(
+        vpk.read_dir("SOUNDS"),
+        Err(VpkError::AmbiguousPath { .. })
+    ));
+    assert!(matches!(
+        vpk.walk("SOUNDS"),
+        Err(VpkError::AmbiguousPath { .. })
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_pattern_filtering() -> Result<()> {
+    use valve_pak::{PackOptions, PathFilter};
+
+    let temp_dir = TempDir::new()?;
+    let source_dir = temp_dir.path().join("source");
+    let vpk_path = temp_dir.path().join("test.vpk");
+
+    create_test_directory(&source_dir)?;
+    fs::create_dir_all(source_dir.join("materials/models"))?;
+    fs::create_dir_all(source_dir.join(".git"))?;
+    fs::write(source_dir.join("materials/models/crate.vmt"), "a")?;
+    fs::write(source_dir.join("materials/models/crate.psd"), "b")?;
+    fs::write(source_dir.join("materials/sky.vmt"), "c")?;
+    fs::write(source_dir.join(".git/config.txt"), "d")?;
+
+    let filter = PathFilter::new().exclude("*.psd")?.exclude(".git/")?;
+    PackOptions::new()
+        .filter(filter)
+        .pack_directory(&source_dir)?
+        .save(&vpk_path)?;
+    let vpk = VPK::open(&vpk_path)?;
+    assert!(!vpk.contains("materials/models/crate.psd"));
+    assert!(!vpk.contains(".git/config.txt"));
+    assert!(vpk.contains("materials/models/crate.vmt"));
+
+    let paths = |entries: Vec<valve_pak::Entry>| -> Vec<String> {
+        entries.iter().map(|e| e.path().to_string()).collect()
+    };
+    assert_eq!(
+        paths(vpk.matching("*.vmt")?),
+        ["materials/models/crate.vmt", "materials/sky.vmt"]
+    );
+    assert_eq!(
+        paths(vpk.matching("materials/models/*.vmt")?),
+        ["materials/models/crate.vmt"]
+    );
+    assert_eq!(
+        paths(vpk.matching(r"regex:^(scripts|sounds)/")?),
+        ["scripts/test.lua", "sounds/beep.wav"]
+    );
+
+    let filter = PathFilter::new().include("materials/")?.exclude("sky.*")?;
+    assert_eq!(paths(vpk.filtered(&filter)), ["materials/models/crate.vmt"]);
+
+    Ok(())
+}
+
+#[test]
+fn test_vpkignore_files() -> Result<()> {
+    use valve_pak::PackOptions;
+
+    let temp_dir = TempDir::new()?;
+    let source_dir = temp_dir.path().join("source");
+
+    create_test_directory(&source_dir)?;
+    fs::create_dir_all(source_dir.join("scripts/vendor"))?;
+    fs::write(source_dir.join(".vpkignore"), "*.bak\nscripts/vendor/\n")?;
+    fs::write(source_dir.join("scripts/.vpkignore"), "!keep.bak\n*.tmp\n")?;
+    fs::write(source_dir.join("readme.bak"), "backup")?;
+    fs::write(source_dir.join("scripts/keep.bak"), "kept")?;
+    fs::write(source_dir.join("scripts/scratch.tmp"), "scratch")?;
+    fs::write(source_dir.join("scripts/vendor/lib.lua"), "vendored")?;
+    fs::write(source_dir.join("config.tmp"), "not ignored at the root")?;
+
+    let vpk = VPK::from_directory(&source_dir)?;
+    assert!(vpk.contains("readme.txt"));
+    assert!(!vpk.contains("readme.bak"));
+    assert!(!vpk.contains(".vpkignore"));
+    assert!(!vpk.contains("scripts/vendor/lib.lua"));
+    // Deeper files override and extend the rules above them
+    assert!(vpk.contains("scripts/keep.bak"));
+    assert!(!vpk.contains("scripts/scratch.tmp"));
+    assert!(vpk.contains("config.tmp"));
+
+    let vpk = PackOptions::new()
+        .ignore_rules(["*.cfg", "sounds/"])
+        .pack_directory(&source_dir)?;
+    assert!(!vpk.contains("config.cfg"));
+    assert!(!vpk.contains("sounds/beep.wav"));
+    assert!(!vpk.contains("readme.bak"));
+
+    let vpk = PackOptions::new()
+        .use_ignore_files(false)
+        .pack_directory(&source_dir)?;
+    assert!(vpk.contains("readme.bak"));
+    assert!(vpk.contains(".vpkignore"));
+    assert!(vpk.contains("scripts/vendor/lib.lua"));
+
+    Ok(())
+}
+
+#[test]
+fn test_pack_from_manifest() -> Result<()> {
+    use valve_pak::{EntryLocation, Manifest, PackOptions};
+
+    let temp_dir = TempDir::new()?;
+    let vpk_path = build_chunked_vpk(
+        &temp_dir,
+        r#"{"files": [
+            {"source": "source/readme.txt", "path": "docs/readme.txt", "preload": 10},
+            {"source": "source/textures/test.dds", "path": "materials/test.dds", "archive": 0},
+            {"source": "source/scripts/test.lua", "path": "scripts/vscripts/test.lua"}
+        ]}"#,
+    )?;
+    let source_dir = temp_dir.path().join("source");
+    let manifest_path = temp_dir.path().join("files.json");
+    assert!(temp_dir.path().join("pak01_000.vpk").exists());
+
+    let vpk = VPK::open(&vpk_path)?;
+    assert_eq!(vpk.file_count(), 3);
+
+    let readme = vpk.entry("docs/readme.txt")?;
+    assert_eq!(readme.preload_length(), 10);
+    assert_eq!(readme.preload(), b"This is a ");
+    assert_eq!(
+        vpk.get_file("docs/readme.txt")?.read_all_string()?,
+        "This is a test readme file.\nSecond line.\n"
+    );
+    assert!(vpk.get_file("docs/readme.txt")?.verify()?);
+
+    assert_eq!(
+        vpk.entry("materials/test.dds")?.location(),
+        EntryLocation::Archive {
+            index: 0,
+            offset: 0
+        }
+    );
+    assert_eq!(vpk.get_file("materials/test.dds")?.read_all()?, [0u8; 256]);
+    assert_eq!(
+        vpk.get_file("scripts/vscripts/test.lua")?
+            .read_all_string()?,
+        "print('Hello from Lua')\n"
+    );
+
+    // Chunked archives need a _dir.vpk name for the chunks to be found
+    assert!(
+        VPK::from_manifest(&manifest_path)?
+            .save(temp_dir.path().join("plain.vpk"))
+            .is_err()
+    );
+
+    // Only the file name is renamed for chunks, not `dir.` in parent directories
+    let nested_dir = temp_dir.path().join("out_dir.d");
+    fs::create_dir(&nested_dir)?;
+    VPK::from_manifest(&manifest_path)?.save(nested_dir.join("pak01_dir.vpk"))?;
+    assert!(nested_dir.join("pak01_000.vpk").exists());
+    assert!(
+        VPK::open(nested_dir.join("pak01_dir.vpk"))?
+            .get_file("materials/test.dds")?
+            .verify()?
+    );
+
+    // Chunk write failures name the chunk, not just the directory file
+    let blocked_dir = temp_dir.path().join("blocked");
+    fs::create_dir_all(blocked_dir.join("pak01_000.vpk"))?;
+    let err = VPK::from_manifest(&manifest_path)?
+        .save(blocked_dir.join("pak01_dir.vpk"))
+        .unwrap_err();
+    assert!(err.to_string().contains("Failed to create archive chunk"));
+
+    // Text manifests map sources to archive paths without hints
+    let text = "readme.txt\nscripts/test.lua => scripts/vscripts/test.lua\n";
+    let vpk = PackOptions::new().pack_manifest(&Manifest::parse_text(text, &source_dir)?)?;
+    assert!(vpk.contains("readme.txt"));
+    assert!(vpk.contains("scripts/vscripts/test.lua"));
+
+    // Archive paths with empty, `.` or `..` segments are rejected
+    for path in ["a/./readme.txt", "a//readme.txt", "a/../readme.txt"] {
+        let text = format!("readme.txt => {path}\n");
+        assert!(matches!(
+            PackOptions::new().pack_manifest(&Manifest::parse_text(&text, &source_dir)?),
+            Err(valve_pak::VpkError::InvalidManifest(_))
+        ));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_pack_multiple_roots() -> Result<()> {
+    use valve_pak::{ConflictPolicy, PackOptions, VpkError};
+
+    let temp_dir = TempDir::new()?;
+    let materials = temp_dir.path().join("build/materials");
+    let scripts = temp_dir.path().join("shared/scripts");
+    let overrides = temp_dir.path().join("overrides");
+
+    fs::create_dir_all(materials.join("models"))?;
+    fs::create_dir_all(&scripts)?;
+    fs::create_dir_all(overrides.join("materials/models"))?;
+    fs::write(materials.join("models/crate.vmt"), "original")?;
+    fs::write(scripts.join("mapspawn.nut"), "print(1)")?;
+    fs::write(overrides.join("materials/models/crate.vmt"), "override")?;
+
+    let vpk = PackOptions::new()
+        .pack_roots([(&materials, "materials"), (&scripts, "/scripts/vscripts/")])?;
+    let mut paths: Vec<_> = vpk.file_paths().cloned().collect();
+    paths.sort();
+    assert_eq!(
+        paths,
+        [
+            "materials/models/crate.vmt",
+            "scripts/vscripts/mapspawn.nut"
+        ]
+    );
+    assert!(matches!(
+        PackOptions::new().pack_roots([(&materials, "../materials")]),
+        Err(VpkError::UnsafePath { .. })
+    ));
+
+    let roots = [(&materials, "materials"), (&overrides, "")];
+    assert!(matches!(
+        PackOptions::new().pack_roots(roots),
+        Err(VpkError::PathConflict { .. })
+    ));
+
+    let vpk_path = temp_dir.path().join("test.vpk");
+    for (policy, expected) in [
+        (ConflictPolicy::FirstWins, "original"),
+        (ConflictPolicy::LastWins, "override"),
+    ] {
+        PackOptions::new()
+            .conflict_policy(policy)
+            .pack_roots(roots)?
+            .save(&vpk_path)?;
+        let vpk = VPK::open(&vpk_path)?;
+        assert_eq!(vpk.file_count(), 1);
+        assert_eq!(
+            vpk.get_file("materials/models/crate.vmt")?
+                .read_all_string()?,
+            expected
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_extract_all() -> Result<()> {
+    use valve_pak::ExtractOptions;
+
+    let temp_dir = TempDir::new()?;
+    let vpk_path = build_chunked_vpk(
+        &temp_dir,
+        r#"[
+            {"source": "source/readme.txt", "path": "readme.txt", "preload": 4},
+            {"source": "source/config.cfg", "path": "config.cfg", "archive": 0},
+            {"source": "source/textures/test.dds", "path": "textures/test.dds", "archive": 1},
+            {"source": "source/sounds/beep.wav", "path": "sounds/beep.wav", "archive": 1}
+        ]"#,
+    )?;
+    let source_dir = temp_dir.path().join("source");
+    let vpk = VPK::open(&vpk_path)?;
+
+    for jobs in [1, 3] {
+        let extract_dir = temp_dir.path().join(format!("extracted_{jobs}"));
+        let count = vpk.extract_all(&extract_dir, &ExtractOptions::new().jobs(jobs))?;
+        assert_eq!(count, 4);
+
+        for path in vpk.file_paths() {
+            assert_eq!(
+                fs::read(extract_dir.join(path))?,
+                fs::read(source_dir.join(path))?,
+                "{path} extracted with {jobs} jobs"
+            );
+        }
+    }
+
+    // Unsafe paths are rejected before anything is written
+    let mut bytes = build_v1_vpk(b"../escape", 0, 0, 4);
+    bytes.extend_from_slice(b"data");
+    let hostile_path = temp_dir.path().join("hostile.vpk");
+    fs::write(&hostile_path, bytes)?;
+    let extract_dir = temp_dir.path().join("hostile");
+    assert!(
+        VPK::open(&hostile_path)?
+            .extract_all(&extract_dir, &ExtractOptions::new())
+            .is_err()
+    );
+    assert!(!extract_dir.exists());
+
+    Ok(())
+}
+
+#[test]
+fn test_verify_files() -> Result<()> {
+    use valve_pak::VerifyOptions;
+
+    let temp_dir = TempDir::new()?;
+    let vpk_path = build_chunked_vpk(
+        &temp_dir,
+        r#"[
+            {"source": "source/readme.txt", "path": "readme.txt", "preload": 4},
+            {"source": "source/config.cfg", "path": "config.cfg", "archive": 0},
+            {"source": "source/textures/test.dds", "path": "textures/test.dds", "archive": 1},
+            {"source": "source/sounds/beep.wav", "path": "sounds/beep.wav", "archive": 1}
+        ]"#,
+    )?;
+    let vpk = VPK::open(&vpk_path)?;
+
+    let report = vpk.verify_files(&VerifyOptions::new().jobs(2))?;
+    assert!(report.is_ok());
+    assert_eq!((report.checked, report.passed), (4, 4));
+
+    // Corrupt the only file in chunk 0 and remove chunk 1
+    let chunk_path = temp_dir.path().join("pak01_000.vpk");
+    let mut chunk = fs::read(&chunk_path)?;
+    chunk[0] ^= 0xff;
+    fs::write(&chunk_path, chunk)?;
+    fs::remove_file(temp_dir.path().join("pak01_001.vpk"))?;
+
+    // Reopen, since the VPK keeps the chunks it already read open
+    let vpk = VPK::open(&vpk_path)?;
+    let report = vpk.verify_files(&VerifyOptions::new())?;
+    assert!(!report.is_ok());
+    assert_eq!(report.failed(), 3);
+    assert_eq!(report.mismatches.len(), 1);
+    assert_eq!(report.mismatches[0].path, "config.cfg");
+    assert_eq!(report.missing_chunks.len(), 1);
+    assert_eq!(report.missing_chunks[0].index, 1);
+    assert_eq!(report.missing_chunks[0].entries, 2);
+
+    let json: serde_json::Value = serde_json::to_value(&report).unwrap();
+    assert_eq!(json["checked"], 4);
+    assert_eq!(json["mismatches"][0]["path"], "config.cfg");
+    assert!(json["elapsed_seconds"].is_f64());
+
+    // Entries without data in a missing chunk are still checked, however
+    // the entries are split into batches
+    let names: Vec<_> = (0..7).map(|i| format!("data{i}")).collect();
+    let mut entries: Vec<_> = names
+        .iter()
+        .enumerate()
+        .map(|(i, name)| (name.as_bytes(), 0, 3, i as u32 * 20, 4))
+        .collect();
+    entries.push((b"empty", 0, 3, 10, 0));
+    let missing_path = temp_dir.path().join("missing_dir.vpk");
+    fs::write(&missing_path, build_v1_vpk_entries(&entries))?;
+    let report = VPK::open(&missing_path)?.verify_files(&VerifyOptions::new().jobs(1))?;
+    assert_eq!((report.checked, report.passed), (8, 1));
+    assert_eq!(report.missing_chunks[0].entries, 7);
+
+    Ok(())
+}
+
+#[test]
+fn test_progress_and_cancellation() -> Result<()> {
+    use std::ops::ControlFlow;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+    use valve_pak::{ExtractOptions, ProgressEvent, VerifyOptions, VpkError};
+
+    let temp_dir = TempDir::new()?;
+    let source_dir = temp_dir.path().join("source");
+    let vpk_path = temp_dir.path().join("test.vpk");
+    create_test_directory(&source_dir)?;
+
+    let files = AtomicUsize::new(0);
+    let bytes = AtomicU64::new(0);
+    let record = |event: &ProgressEvent<'_>| {
+        files.fetch_max(event.files_done, Ordering::Relaxed);
+        bytes.fetch_max(event.bytes_done, Ordering::Relaxed);
+        assert_eq!(event.files_total, 5);
+        ControlFlow::Continue(())
+    };
+    VPK::from_directory(&source_dir)?.save_with_progress(&vpk_path, &record)?;
+    assert_eq!(files.load(Ordering::Relaxed), 5);
+    assert_eq!(bytes.load(Ordering::Relaxed), 361);
+
+    let vpk = VPK::open(&vpk_path)?;
+    let seen = Arc::new(AtomicUsize::new(0));
+    let counter = Arc::clone(&seen);
+    let report = vpk.verify_files(&VerifyOptions::new().progress(Arc::new(
+        move |_: &ProgressEvent<'_>| {
+            counter.fetch_add(1, Ordering::Relaxed);
+            ControlFlow::Continue(())
+        },
+    )))?;
+    assert!(report.is_ok());
+    assert_eq!(seen.load(Ordering::Relaxed), 5);
+
+    // Stopping after the first file cancels the remaining work
+    let stop = |_: &ProgressEvent<'_>| ControlFlow::Break(());
+    let options = ExtractOptions::new().jobs(1).progress(Arc::new(stop));
+    assert!(matches!(
+        vpk.extract_all(temp_dir.path().join("extracted"), &options),
+        Err(VpkError::Cancelled)
+    ));
+    assert_eq!(fs::read_dir(temp_dir.path().join("extracted"))?.count(), 1);
+    assert!(matches!(
+        vpk.verify_files(&VerifyOptions::new().progress(Arc::new(stop))),
+        Err(VpkError::Cancelled)
+    ));
+
+    // A cancelled save removes the directory file and chunks it had written
+    let manifest_path = temp_dir.path().join("files.json");
+    fs::write(
+        &manifest_path,
+        r#"[
+            {"source": "source/readme.txt", "path": "readme.txt", "archive": 0},
+            {"source": "source/config.cfg", "path": "config.cfg", "archive": 0}
+        ]"#,
+    )?;
+    let cancelled_path = temp_dir.path().join("cancelled_dir.vpk");
+    assert!(matches!(
+        VPK::from_manifest(&manifest_path)?.save_with_progress(&cancelled_path, &stop),
+        Err(VpkError::Cancelled)
+    ));
+    assert!(!cancelled_path.exists());
+    assert!(!temp_dir.path().join("cancelled_000.vpk").exists());
+
+    Ok(())
+}
+
+#[test]
+fn test_concurrent_reads() -> Result<()> {
+    use std::sync::Arc;
+
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<VPK>();
+    assert_send_sync::<valve_pak::VPKFile>();
+
+    let temp_dir = TempDir::new()?;
+    let vpk_path = build_chunked_vpk(
+        &temp_dir,
+        r#"[
+            {"source": "source/readme.txt", "path": "readme.txt", "preload": 4, "archive": 0},
+            {"source": "source/config.cfg", "path": "config.cfg", "archive": 0},
+            {"source": "source/textures/test.dds", "path": "textures/test.dds", "archive": 1}
+        ]"#,
+    )?;
+    let source_dir = temp_dir.path().join("source");
+    let vpk = Arc::new(VPK::open(&vpk_path)?);
+
+    // Interleaved cursors over the same chunk do not disturb each other
+    let mut readme = vpk.get_file("readme.txt")?;
+    let mut config = vpk.get_file("config.cfg")?;
+    let mut start = [0u8; 6];
+    readme.read_exact(&mut start)?;
+    assert_eq!(
+        config.read_all_string()?,
+        "setting1=value1\nsetting2=value2\n"
+    );
+    let mut rest = String::new();
+    readme.read_to_string(&mut rest)?;
+    assert_eq!(rest, "s a test readme file.\nSecond line.\n");
+
+    let workers: Vec<_> = (0..4)
+        .map(|_| {
+            let vpk = Arc::clone(&vpk);
+            let source_dir = source_dir.clone();
+            std::thread::spawn(move || -> Result<()> {
+                for _ in 0..10 {
+                    for path in ["readme.txt", "config.cfg", "textures/test.dds"] {
+                        assert_eq!(
+                            vpk.get_file(path)?.read_all()?,
+                            fs::read(source_dir.join(path))?
+                        );
+                    }
+                }
+                Ok(())
+            })
+        })
+        .collect();
+    for worker in workers {
+        worker.join().unwrap()?;
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_repack_opened_vpk() -> Result<()> {
+    use valve_pak::VerifyOptions;
+
+    let temp_dir = TempDir::new()?;
+    let repacked_path = temp_dir.path().join("pak02_dir.vpk");
+    let vpk_path = build_chunked_vpk(
+        &temp_dir,
+        r#"[
+            {"source": "source/readme.txt", "path": "readme.txt", "preload": 4},
+            {"source": "source/config.cfg", "path": "config.cfg", "preload": 8, "archive": 0},
+            {"source": "source/textures/test.dds", "path": "textures/test.dds", "archive": 1},
+            {"source": "source/sounds/beep.wav", "path": "sounds/beep.wav", "archive": 1}
+        ]"#,
+    )?;
+    let source_dir = temp_dir.path().join("source");
+
+    // Entry data is copied from the opened VPK's directory file and chunks
+    let vpk = VPK::open(&vpk_path)?;
+    vpk.save(&repacked_path)?;
+    assert!(temp_dir.path().join("pak02_000.vpk").exists());
+    assert!(temp_dir.path().join("pak02_001.vpk").exists());
+
+    let repacked = VPK::open(&repacked_path)?;
+    assert_eq!(repacked.file_count(), 4);
+    for path in [
+        "readme.txt",
+        "config.cfg",
+        "textures/test.dds",
+        "sounds/beep.wav",
+    ] {
+        assert_eq!(
+            repacked.get_file(path)?.read_all()?,
+            fs::read(source_dir.join(path))?
+        );
+    }
+    assert!(repacked.verify_files(&VerifyOptions::new())?.is_ok());
+
+    // The opened file cannot be overwritten while its data is being copied
+    assert!(matches!(
+        vpk.save(&vpk_path),
+        Err(valve_pak::VpkError::Unsupported(_))
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_direct_reads() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let vpk_path = build_chunked_vpk(
+        &temp_dir,
+        r#"[
+            {"source": "source/readme.txt", "path": "readme.txt", "preload": 4, "archive": 0},
+            {"source": "source/config.cfg", "path": "config.cfg"},
+            {"source": "source/textures/test.dds", "path": "textures/test.dds", "archive": 1}
+        ]"#,
+    )?;
+    let unsaved = VPK::from_manifest(temp_dir.path().join("files.json"))?;
+    let vpk = VPK::open(&vpk_path)?;
+
+    let readme = "This is a test readme file.\nSecond line.\n";
+    for vpk in [&unsaved, &vpk] {
+        assert_eq!(vpk.read("readme.txt")?, readme.as_bytes());
+        assert_eq!(vpk.read_to_string("readme.txt")?, readme);
+        assert_eq!(vpk.read("textures/test.dds")?, vec![0u8; 256]);
+
+        // Ranges spanning the preload bytes and the archive data
+        assert_eq!(
+            vpk.read_range("readme.txt", 2, 6)?,
+            &readme.as_bytes()[2..8]
+        );
+        assert_eq!(vpk.read_range("readme.txt", 5, 4)?, b"is a");
+        assert_eq!(vpk.read_range("readme.txt", 35, 100)?, b"line.\n");
+        assert!(vpk.read_range("readme.txt", 100, 4)?.is_empty());
+
+        let mut output = Vec::new();
+        assert_eq!(vpk.copy_to("config.cfg", &mut output)?, 32);
+        assert_eq!(output, b"setting1=value1\nsetting2=value2\n");
+    }
+
+    assert!(matches!(
+        vpk.read("missing.txt"),
+        Err(valve_pak::VpkError::FileNotFound(_))
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_info() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let vpk_path = build_chunked_vpk(
+        &temp_dir,
+        r#"[
+            {"source": "source/readme.txt", "path": "readme.txt", "preload": 4},
+            {"source": "source/config.cfg", "path": "config.cfg", "archive": 0},
+            {"source": "source/textures/test.dds", "path": "textures/test.dds", "archive": 2},
+            {"source": "source/sounds/beep.wav", "path": "sounds/beep.wav", "archive": 2},
+            {"source": "source/sounds/beep.wav", "path": "sounds/short.wav", "preload": 8, "archive": 2}
+        ]"#,
+    )?;
+    fs::remove_file(temp_dir.path().join("pak01_000.vpk"))?;
+
+    let info = VPK::open(&vpk_path)?.info()?;
+    assert_eq!(info.file_size, fs::metadata(&vpk_path)?.len());
+    assert_eq!(info.sections.tree.offset, 28);
+    assert_eq!(info.sections.embedded_data.length, 37);
+    assert_eq!(info.sections.signature.end(), info.file_size);
+    assert_eq!(info.trailing_bytes, 0);
+    // The preload-only entry counts as embedded, not as a chunk entry
+    assert_eq!((info.entries, info.embedded_entries), (5, 2));
+
+    assert_eq!(info.chunks.len(), 2);
+    assert_eq!(info.chunks[0].index, 0);
+    assert_eq!(info.chunks[0].file_size, None);
+    assert_eq!(info.chunks[1].index, 2);
+    assert_eq!(info.chunks[1].entries, 2);
+    assert_eq!(info.chunks[1].data_length, 264);
+    assert_eq!(info.chunks[1].file_size, Some(264));
+
+    // Bytes appended after the signature section are reported
+    let mut data = fs::read(&vpk_path)?;
+    data.extend_from_slice(b"junk");
+    fs::write(&vpk_path, data)?;
+    let info = VPK::open(&vpk_path)?.info()?;
+    assert_eq!(info.trailing_bytes, 4);
+
+    let json = serde_json::to_value(&info)?;
+    assert_eq!(json["header"]["version"], 2);
+    assert_eq!(
+        json["checksums"]["tree_checksum"].as_str().map(str::len),
+        Some(32)
+    );
+    assert_eq!(json["chunks"][0]["file_size"], serde_json::Value::Null);
+
+    // V1 embedded data ends with the last embedded entry; offsets into
+    // chunks do not count
+    let mut bytes =
+        build_v1_vpk_entries(&[(b"data", 0, EMBEDDED, 0, 4), (b"empty", 0, 3, 1_000_000, 0)]);
+    bytes.extend_from_slice(b"data");
+    bytes.extend_from_slice(b"trailing");
+    let v1_path = temp_dir.path().join("v1.vpk");
+    fs::write(&v1_path, &bytes)?;
+    let info = VPK::open(&v1_path)?.info()?;
+    assert_eq!(info.trailing_bytes, 8);
+    assert!(info.chunks.is_empty());
+
+    Ok(())
+}
+
+#[cfg(feature = "tokio")]
+#[tokio::test]
+async fn test_async_api() -> Result<()> {
+    use std::sync::Arc;
+    use tokio::io::{AsyncReadExt, AsyncSeekExt};
+    use valve_pak::ExtractOptions;
+
+    let temp_dir = TempDir::new()?;
+    let vpk_path = build_chunked_vpk(
+        &temp_dir,
+        r#"[
+            {"source": "source/readme.txt", "path": "readme.txt", "preload": 4, "archive": 0},
+            {"source": "source/config.cfg", "path": "config.cfg"},
+            {"source": "source/textures/test.dds", "path": "textures/test.dds", "archive": 1}
+        ]"#,
+    )?;
+    let source_dir = temp_dir.path().join("source");
+    let vpk = Arc::new(VPK::open_async(&vpk_path).await?);
+
+    for path in ["readme.txt", "config.cfg", "textures/test.dds"] {
+        let mut data = Vec::new();
+        vpk.get_async_file(path)?.read_to_end(&mut data).await?;
+        assert_eq!(data, fs::read(source_dir.join(path))?, "{path}");
+    }
+
+    // Seeking works across the preload and archive parts
+    let mut readme = vpk.get_async_file("readme.txt")?;
+    assert_eq!(readme.seek(SeekFrom::Start(2)).await?, 2);
+    let mut middle = [0u8; 6];
+    readme.read_exact(&mut middle).await?;
+    assert_eq!(&middle, b"is is ");
+    assert_eq!(readme.seek(SeekFrom::End(-6)).await?, 35);
+    let mut tail = String::new();
+    readme.read_to_string(&mut tail).await?;
+    assert_eq!(tail, "line.\n");
+    assert!(readme.seek(SeekFrom::Current(-100)).await.is_err());
+
+    let extract_dir = temp_dir.path().join("extracted");
+    let count = Arc::clone(&vpk)
+        .extract_all_async(&extract_dir, ExtractOptions::new())
+        .await?;
+    assert_eq!(count, 3);
+    assert_eq!(
+        fs::read(extract_dir.join("textures/test.dds"))?,
+        vec![0u8; 256]
+    );
+
+    // A truncated chunk is an error rather than an early end of file
+    fs::OpenOptions::new()
+        .write(true)
+        .open(temp_dir.path().join("pak01_001.vpk"))?
+        .set_len(10)?;
+    let mut data = Vec::new();
+    let error = vpk
+        .get_async_file("textures/test.dds")?
+        .read_to_end(&mut data)
+        .await
+        .unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::UnexpectedEof);
+
+    Ok(())
+}